@@ -0,0 +1,194 @@
+// a chess engine speaking the Universal Chess Interface over stdin and stdout, for playing in GUIs
+// like Cute Chess or Arena
+
+use std::io::{self, BufRead};
+use std::num::IntErrorKind;
+use std::time::{Duration, Instant};
+
+use redkar_chess::{Color, Engine, Game, Score, SearchLimits, SearchResult};
+
+// the range of the Hash option, in megabytes
+const MIN_HASH: u64 = 1;
+const MAX_HASH: u64 = 4096;
+
+fn main() {
+    let mut engine = Engine::new();
+    let mut game = Game::new_game();
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else { break };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.first().copied() {
+            Some("uci") => {
+                println!("id name Redkar {}", env!("CARGO_PKG_VERSION"));
+                println!("id author the redkar-chess developers");
+                println!("option name Hash type spin default 16 min {} max {}", MIN_HASH, MAX_HASH);
+                println!("option name Clear Hash type button");
+                println!("uciok");
+            }
+            Some("isready") => println!("readyok"),
+            Some("ucinewgame") => {
+                engine.stop();
+                if let Some(searcher) = engine.searcher() {
+                    searcher.clear();
+                }
+                game = Game::new_game();
+            }
+            Some("setoption") => set_option(&mut engine, &words[1..]),
+            Some("position") => match parse_position(&words[1..]) {
+                Ok(position) => game = position,
+                Err(error) => println!("info string {}", error),
+            },
+            Some("go") => go(&mut engine, &game, &words[1..]),
+            Some("stop") => engine.stop(),
+            Some("quit") => break,
+            // anything else is ignored, as the protocol asks
+            _ => {}
+        }
+    }
+    engine.stop();
+}
+
+// "setoption name <name> value <value>", where the name can be more than one word
+fn set_option(engine: &mut Engine, args: &[&str]) {
+    let value_at = args.iter().position(|&word| word == "value").unwrap_or(args.len());
+    let name = args.get(1..value_at).unwrap_or_default().join(" ");
+    let value = args.get(value_at + 1..).unwrap_or_default().join(" ");
+    // the protocol only allows this between searches, during go infinite there is no searcher to set
+    let Some(searcher) = engine.searcher() else {
+        println!("info string can't set {} while searching", name);
+        return;
+    };
+    match name.to_lowercase().as_str() {
+        "hash" => match hash_size(&value) {
+            Some(megabytes) => searcher.set_table_size(megabytes),
+            None => println!("info string invalid hash size {}", value),
+        },
+        "clear hash" => searcher.clear(),
+        _ => println!("info string unknown option {}", name),
+    }
+}
+
+// the Hash value in megabytes, brought into the advertised range like GUIs expect
+fn hash_size(value: &str) -> Option<usize> {
+    let megabytes = match value.parse::<u64>() {
+        Ok(megabytes) => megabytes,
+        Err(error) if *error.kind() == IntErrorKind::PosOverflow => MAX_HASH,
+        Err(_) => return None,
+    };
+    Some(megabytes.clamp(MIN_HASH, MAX_HASH) as usize)
+}
+
+// "startpos" or "fen <fen>", optionally followed by "moves" and the moves played since
+fn parse_position(args: &[&str]) -> Result<Game, String> {
+    let moves_at = args.iter().position(|&word| word == "moves").unwrap_or(args.len());
+    let mut game = match args.first() {
+        Some(&"startpos") => Game::new_game(),
+        Some(&"fen") => Game::from_fen(&args[1..moves_at].join(" ")).map_err(|error| error.to_string())?,
+        _ => return Err("expected startpos or fen".to_string()),
+    };
+    for &word in args.iter().skip(moves_at + 1) {
+        let mv = game.parse_uci(word).map_err(|error| format!("{}: {}", word, error))?;
+        game.do_move(mv).map_err(|error| format!("{}: {:?}", word, error))?;
+    }
+    Ok(game)
+}
+
+fn go(engine: &mut Engine, game: &Game, args: &[&str]) {
+    let (limits, infinite) = parse_go(args, game.turn);
+    let start = Instant::now();
+    engine.go(game, limits, infinite, move |result| println!("{}", info(result, start.elapsed())), |result| match result {
+        Some(result) => match result.pv.get(1) {
+            Some(ponder) => println!("bestmove {} ponder {}", result.best_move, ponder),
+            None => println!("bestmove {}", result.best_move),
+        },
+        // no legal moves, the game is over
+        None => println!("bestmove 0000"),
+    });
+}
+
+// the limits for "go", and whether it was "go infinite"
+fn parse_go(args: &[&str], turn: Color) -> (SearchLimits, bool) {
+    let mut limits = SearchLimits::default();
+    let mut infinite = false;
+    let mut clock = [None; 2];
+    let mut increment = [0; 2];
+    let mut moves_to_go = None;
+    let mut words = args.iter();
+    while let Some(&word) = words.next() {
+        // some GUIs send negative times once the clock has run out
+        let mut number = || words.next().and_then(|word| word.parse::<i64>().ok()).map(|number| number.max(0) as u64);
+        match word {
+            "depth" => limits.depth = number().map(|depth| depth as u32),
+            "nodes" => limits.nodes = number(),
+            "movetime" => limits.time = number().map(Duration::from_millis),
+            "wtime" => clock[Color::White as usize] = number(),
+            "btime" => clock[Color::Black as usize] = number(),
+            "winc" => increment[Color::White as usize] = number().unwrap_or(0),
+            "binc" => increment[Color::Black as usize] = number().unwrap_or(0),
+            "movestogo" => moves_to_go = number().map(|moves| moves as u32),
+            "infinite" => infinite = true,
+            _ => {}
+        }
+    }
+    if let (Some(remaining), None) = (clock[turn as usize], limits.time) {
+        let increment = Duration::from_millis(increment[turn as usize]);
+        limits.time = SearchLimits::for_clock(Duration::from_millis(remaining), increment, moves_to_go).time;
+    }
+    (limits, infinite)
+}
+
+fn info(result: &SearchResult, elapsed: Duration) -> String {
+    let score = match result.score {
+        Score::Centipawns(centipawns) => format!("cp {}", centipawns),
+        Score::Mate(moves) => format!("mate {}", moves),
+    };
+    let millis = elapsed.as_millis() as u64;
+    let pv: Vec<String> = result.pv.iter().map(|mv| mv.to_string()).collect();
+    format!(
+        "info depth {} score {} nodes {} nps {} time {} pv {}",
+        result.depth,
+        score,
+        result.nodes,
+        result.nodes * 1000 / millis.max(1),
+        millis,
+        pv.join(" ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn uci_commands() {
+        let game = parse_position(&["startpos", "moves", "e2e4", "e7e5", "e1e2"]).unwrap();
+        assert_eq!(game.to_fen(), "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPPKPPP/RNBQ1BNR b kq - 1 2");
+        // castling may be written as the king taking its own rook
+        let game = parse_position(&["fen", "4k3/8/8/8/8/8/8/4K2R", "w", "K", "-", "0", "1", "moves", "e1h1"]).unwrap();
+        assert_eq!(game.to_fen(), "4k3/8/8/8/8/8/8/5RK1 b - - 1 1");
+        assert!(parse_position(&["startpos", "moves", "e2e5"]).is_err());
+        assert!(parse_position(&["nowhere"]).is_err());
+
+        assert_eq!(hash_size("64"), Some(64));
+        assert_eq!(hash_size("0"), Some(1));
+        assert_eq!(hash_size("99999999999999"), Some(4096));
+        assert_eq!(hash_size("999999999999999999999999"), Some(4096));
+        assert_eq!(hash_size("-5"), None);
+        assert_eq!(hash_size("lots"), None);
+
+        let (limits, infinite) = parse_go(&["depth", "5"], Color::White);
+        assert_eq!((limits.depth, limits.time, infinite), (Some(5), None, false));
+        let (limits, infinite) = parse_go(&["infinite"], Color::White);
+        assert_eq!((limits, infinite), (SearchLimits::default(), true));
+        // the clock of the side to move decides the time
+        let (limits, _) = parse_go(&["wtime", "60000", "btime", "-20", "winc", "1000", "binc", "1000"], Color::White);
+        assert_eq!(limits.time, Some(Duration::from_millis(2750)));
+        let (limits, _) = parse_go(&["wtime", "60000", "btime", "-20", "winc", "1000", "binc", "1000"], Color::Black);
+        assert_eq!(limits.time, Some(Duration::ZERO));
+        let (limits, _) = parse_go(&["wtime", "60000", "movetime", "500"], Color::White);
+        assert_eq!(limits.time, Some(Duration::from_millis(500)));
+
+        let result = SearchResult { best_move: game.legal_moves()[0], score: Score::Mate(-3), pv: vec![], depth: 4, nodes: 3000 };
+        assert!(info(&result, Duration::from_millis(1500)).starts_with("info depth 4 score mate -3 nodes 3000 nps 2000 time 1500 pv"));
+    }
+}
@@ -0,0 +1,314 @@
+// a chess engine speaking the Chess Engine Communication Protocol (version 2) over stdin and
+// stdout, for XBoard, WinBoard and other GUIs and tools that don't speak UCI
+
+use std::io::{self, BufRead};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use redkar_chess::{Color, Decision, Engine, Game, Score, SearchLimits, SearchResult, Termination};
+
+fn main() {
+    let mut xboard = XBoard::new();
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else { break };
+        if !xboard.command(&line) {
+            break;
+        }
+    }
+}
+
+// the time control set with level, st and sd, and the engine's clock as told by time
+struct TimeControl {
+    // 0 when the base time is for the whole game
+    moves_per_session: u32,
+    increment: Duration,
+    per_move: Option<Duration>,
+    depth: Option<u32>,
+    remaining: Duration,
+}
+
+impl Default for TimeControl {
+    fn default() -> TimeControl {
+        TimeControl {
+            moves_per_session: 0,
+            increment: Duration::ZERO,
+            per_move: None,
+            depth: None,
+            remaining: Duration::from_secs(300),
+        }
+    }
+}
+
+impl TimeControl {
+    fn limits(&self, game: &Game) -> SearchLimits {
+        let mut limits = match self.per_move {
+            Some(time) => SearchLimits { time: Some(time), ..Default::default() },
+            None => {
+                let session = self.moves_per_session;
+                let moves_to_go = (session > 0).then(|| session - (game.fullmove_number - 1) % session);
+                SearchLimits::for_clock(self.remaining, self.increment, moves_to_go)
+            }
+        };
+        limits.depth = self.depth;
+        limits
+    }
+}
+
+struct XBoard {
+    engine: Engine,
+    // shared with the search, which plays the engine's move on it once it is done
+    game: Arc<Mutex<Game>>,
+    // counts up with every search started or dropped, only the latest search gets to move
+    search: Arc<AtomicUsize>,
+    // None in force mode, when the engine only keeps track of the moves
+    engine_color: Option<Color>,
+    time_control: TimeControl,
+    post: bool,
+}
+
+impl XBoard {
+    fn new() -> XBoard {
+        XBoard {
+            engine: Engine::new(),
+            game: Arc::new(Mutex::new(Game::new_game())),
+            search: Arc::new(AtomicUsize::new(0)),
+            engine_color: Some(Color::Black),
+            time_control: TimeControl::default(),
+            post: false,
+        }
+    }
+
+    // handles one line from the GUI, false once it's time to quit
+    fn command(&mut self, line: &str) -> bool {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let number = |i: usize| words.get(i).and_then(|word| word.parse::<u64>().ok());
+        match words.first().copied() {
+            Some("protover") => {
+                println!("feature myname=\"Redkar {}\" ping=1 setboard=1 usermove=1 time=1 draw=0 sigint=0 sigterm=0 reuse=1 analyze=0 colors=0 done=1", env!("CARGO_PKG_VERSION"));
+            }
+            Some("new") => {
+                self.drop_search();
+                *self.game.lock().unwrap() = Game::new_game();
+                if let Some(searcher) = self.engine.searcher() {
+                    searcher.clear();
+                }
+                self.engine_color = Some(Color::Black);
+                self.time_control.depth = None;
+            }
+            Some("force") | Some("result") => {
+                self.drop_search();
+                self.engine_color = None;
+            }
+            Some("go") => {
+                self.drop_search();
+                self.engine_color = Some(self.game.lock().unwrap().turn);
+                self.think();
+            }
+            // move now
+            Some("?") => self.engine.stop(),
+            Some("usermove") => {
+                self.drop_search();
+                let Some(&mv) = words.get(1) else { return true };
+                let played = {
+                    let mut game = self.game.lock().unwrap();
+                    let played = game.parse_uci(mv).ok().and_then(|parsed| game.do_move(parsed).ok()).is_some();
+                    if let Some(result) = result_line(&game) {
+                        println!("{}", result);
+                    }
+                    played
+                };
+                if played {
+                    self.think_if_engine_to_move();
+                }
+                else {
+                    println!("Illegal move: {}", mv);
+                }
+            }
+            Some("undo") => {
+                self.drop_search();
+                self.game.lock().unwrap().undo_move();
+            }
+            // takes back a move of each side, the user gets to move again
+            Some("remove") => {
+                self.drop_search();
+                let mut game = self.game.lock().unwrap();
+                game.undo_move();
+                game.undo_move();
+            }
+            Some("setboard") => {
+                self.drop_search();
+                match Game::from_fen(&words[1..].join(" ")) {
+                    Ok(game) => *self.game.lock().unwrap() = game,
+                    Err(error) => println!("tellusererror Illegal position: {}", error),
+                }
+            }
+            Some("level") => match parse_level(&words[1..]) {
+                // only the clock, a depth or time per move set with sd or st stays
+                Some((moves_per_session, base, increment)) => {
+                    self.time_control.moves_per_session = moves_per_session;
+                    self.time_control.increment = increment;
+                    self.time_control.remaining = base;
+                }
+                None => println!("Error (malformed level): {}", line),
+            },
+            Some("st") => self.time_control.per_move = number(1).map(Duration::from_secs),
+            Some("sd") => self.time_control.depth = number(1).map(|depth| depth as u32),
+            // the engine's clock, in centiseconds
+            Some("time") => {
+                if let Some(centiseconds) = number(1) {
+                    self.time_control.remaining = Duration::from_millis(centiseconds * 10);
+                }
+            }
+            Some("post") => self.post = true,
+            Some("nopost") => self.post = false,
+            Some("ping") => println!("pong {}", words.get(1).unwrap_or(&"")),
+            Some("quit") => {
+                self.drop_search();
+                return false;
+            }
+            // xboard, accepted, otim, hard, easy, computer and the rest need nothing done
+            _ => {}
+        }
+        true
+    }
+
+    fn think_if_engine_to_move(&mut self) {
+        let game = self.game.lock().unwrap();
+        let to_move = Some(game.turn) == self.engine_color && game.termination().is_none();
+        drop(game);
+        if to_move {
+            self.think();
+        }
+    }
+
+    // starts a search, which plays its move and tells the GUI once it's done
+    fn think(&mut self) {
+        let game = self.game.lock().unwrap().clone();
+        let limits = self.time_control.limits(&game);
+        let id = self.search.fetch_add(1, Ordering::SeqCst) + 1;
+        let search = self.search.clone();
+        let shared_game = self.game.clone();
+        let post = self.post;
+        let start = Instant::now();
+        let report = move |result: &SearchResult| {
+            if post {
+                println!("{}", thinking(result, start.elapsed()));
+            }
+        };
+        let done = move |result: Option<SearchResult>| {
+            // a search dropped by force, undo and the like doesn't move
+            if search.load(Ordering::SeqCst) != id {
+                return;
+            }
+            let Some(result) = result else { return };
+            let mut game = shared_game.lock().unwrap();
+            game.do_move(result.best_move).unwrap();
+            println!("move {}", result.best_move);
+            if let Some(result) = result_line(&game) {
+                println!("{}", result);
+            }
+        };
+        self.engine.go(&game, limits, false, report, done);
+    }
+
+    // stops the search without playing its move
+    fn drop_search(&mut self) {
+        self.search.fetch_add(1, Ordering::SeqCst);
+        self.engine.stop();
+    }
+}
+
+// "level MPS BASE INC", the base time in minutes or as minutes:seconds and the increment in
+// seconds, like "level 40 5 0" or "level 0 2:30 1.5"
+fn parse_level(args: &[&str]) -> Option<(u32, Duration, Duration)> {
+    let [moves, base, increment] = args else { return None };
+    let base = match base.split_once(':') {
+        Some((minutes, seconds)) => 60 * minutes.parse::<u64>().ok()? + seconds.parse::<u64>().ok()?,
+        None => 60 * base.parse::<u64>().ok()?,
+    };
+    let increment = increment.parse::<f64>().ok().filter(|increment| *increment >= 0.0)?;
+    Some((moves.parse().ok()?, Duration::from_secs(base), Duration::from_secs_f64(increment)))
+}
+
+// a line of thinking output: depth, score in centipawns, time in centiseconds, nodes and the
+// principal variation. mates are written as 100000 plus the moves to mate, as is usual
+fn thinking(result: &SearchResult, elapsed: Duration) -> String {
+    let score = match result.score {
+        Score::Centipawns(centipawns) => centipawns,
+        Score::Mate(moves) if moves > 0 => 100000 + moves,
+        Score::Mate(moves) => -100000 + moves,
+    };
+    let pv: Vec<String> = result.pv.iter().map(|mv| mv.to_string()).collect();
+    format!("{} {} {} {} {}", result.depth, score, elapsed.as_millis() / 10, result.nodes, pv.join(" "))
+}
+
+// the result to tell the GUI once the game is over
+fn result_line(game: &Game) -> Option<String> {
+    let decision = game.decision()?;
+    let reason = match game.termination()? {
+        Termination::Checkmate if decision == Decision::White => "White mates",
+        Termination::Checkmate => "Black mates",
+        Termination::Stalemate => "Stalemate",
+        Termination::FiftyMoveRule | Termination::SeventyFiveMoveRule => "Draw by fifty move rule",
+        Termination::ThreefoldRepetition | Termination::FivefoldRepetition => "Draw by repetition",
+        Termination::InsufficientMaterial => "Draw by insufficient material",
+    };
+    let result = match decision {
+        Decision::White => "1-0",
+        Decision::Black => "0-1",
+        Decision::Tie => "1/2-1/2",
+    };
+    Some(format!("{} {{{}}}", result, reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn xboard_commands() {
+        let mut xboard = XBoard::new();
+        let fen = |xboard: &XBoard| xboard.game.lock().unwrap().to_fen();
+        for command in ["xboard", "protover 2", "new", "sd 2", "usermove e2e4"] {
+            assert!(xboard.command(command));
+        }
+        // the engine plays black and answers the move
+        xboard.engine.wait();
+        assert_eq!(xboard.game.lock().unwrap().turn, Color::White);
+
+        // in force mode it only keeps track of the moves
+        xboard.command("new");
+        for command in ["force", "usermove e2e4", "usermove e7e5", "usermove e1e3", "undo"] {
+            xboard.command(command);
+        }
+        assert_eq!(fen(&xboard), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+        xboard.command("setboard 7k/8/6K1/8/8/8/8/R7 w - - 0 1");
+        xboard.command("setboard not a position");
+        assert_eq!(fen(&xboard), "7k/8/6K1/8/8/8/8/R7 w - - 0 1");
+        xboard.command("sd 3");
+        xboard.command("go");
+        xboard.engine.wait();
+        assert_eq!(xboard.game.lock().unwrap().decision(), Some(Decision::White));
+        assert_eq!(result_line(&xboard.game.lock().unwrap()).unwrap(), "1-0 {White mates}");
+        xboard.command("remove");
+        assert_eq!(fen(&xboard), "7k/8/6K1/8/8/8/8/R7 w - - 0 1");
+        assert!(!xboard.command("quit"));
+
+        assert_eq!(parse_level(&["40", "5", "0"]), Some((40, Duration::from_secs(300), Duration::ZERO)));
+        assert_eq!(parse_level(&["0", "2:30", "1.5"]), Some((0, Duration::from_secs(150), Duration::from_millis(1500))));
+        assert_eq!(parse_level(&["0", "2:30"]), None);
+        let mut xboard = XBoard::new();
+        for command in ["sd 4", "st 5", "level 40 5 0"] {
+            xboard.command(command);
+        }
+        let time_control = &xboard.time_control;
+        assert_eq!((time_control.depth, time_control.per_move), (Some(4), Some(Duration::from_secs(5))));
+        assert_eq!((time_control.moves_per_session, time_control.remaining), (40, Duration::from_secs(300)));
+        let time_control = TimeControl { moves_per_session: 40, remaining: Duration::from_secs(100), ..TimeControl::default() };
+        assert_eq!(time_control.limits(&Game::new_game()).time, Some(Duration::from_millis(2500)));
+
+        let result = SearchResult { best_move: Game::new_game().legal_moves()[0], score: Score::Mate(-2), pv: vec![], depth: 6, nodes: 900 };
+        assert_eq!(thinking(&result, Duration::from_millis(1234)), "6 -100002 123 900 ");
+    }
+}
@@ -0,0 +1,257 @@
+use std::sync::OnceLock;
+
+use crate::{Color, Square};
+
+// one bit per square, bit 0 is a1, bit 7 is h1 and bit 63 is h8
+pub(crate) type Bitboard = u64;
+
+pub(crate) const RANK_1: Bitboard = 0xff;
+pub(crate) const RANK_8: Bitboard = 0xff << 56;
+pub(crate) const LIGHT_SQUARES: Bitboard = 0x55aa_55aa_55aa_55aa;
+
+pub(crate) fn bit(square: Square) -> Bitboard {
+    1 << square.index()
+}
+
+// the squares of a bitboard, from a1 towards h8
+pub(crate) struct Squares(Bitboard);
+
+impl Iterator for Squares {
+    type Item = Square;
+
+    fn next(&mut self) -> Option<Square> {
+        if self.0 == 0 {
+            return None;
+        }
+        let square = Square::from_index(self.0.trailing_zeros() as usize);
+        self.0 &= self.0 - 1;
+        Some(square)
+    }
+}
+
+pub(crate) fn squares(bitboard: Bitboard) -> Squares {
+    Squares(bitboard)
+}
+
+pub(crate) fn knight_attacks(square: Square) -> Bitboard {
+    tables().knight[square.index()]
+}
+
+pub(crate) fn king_attacks(square: Square) -> Bitboard {
+    tables().king[square.index()]
+}
+
+// the squares a pawn of `color` on the square captures on
+pub(crate) fn pawn_attacks(color: Color, square: Square) -> Bitboard {
+    tables().pawn[color as usize][square.index()]
+}
+
+pub(crate) fn bishop_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    let tables = tables();
+    tables.attacks[tables.bishop[square.index()].index(occupied)]
+}
+
+pub(crate) fn rook_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    let tables = tables();
+    tables.attacks[tables.rook[square.index()].index(occupied)]
+}
+
+// the squares strictly between two squares on the same rank, file or diagonal, empty otherwise
+pub(crate) fn between(a: Square, b: Square) -> Bitboard {
+    tables().between[a.index()][b.index()]
+}
+
+const ROOK_DIRECTIONS: [(isize, isize); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+const BISHOP_DIRECTIONS: [(isize, isize); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+// magic bitboards: the occupied squares that can block a slider are multiplied by a number that
+// was searched for to map every set of blockers onto its own slot, so looking up the attacks is a
+// multiplication and a shift
+const ROOK_MAGICS: [u64; 64] = [
+    0x1080004008801020, 0x0840092002c03000, 0x1900200010400900, 0x0880100008000480,
+    0x4200100420080200, 0x8100020100080400, 0x0200040110886200, 0x0200008040220411,
+    0x0404800084400220, 0x0000401000402000, 0x0086001081220440, 0x0408800800100280,
+    0x000a001201040820, 0x8848800200840080, 0x4001000100040200, 0x0442000102105084,
+    0x9080010020804100, 0x0040404000201009, 0x0000808010002009, 0x2200090021d00100,
+    0x0008008008040080, 0x0004004002010040, 0x0011040008015042, 0x00000a0001768104,
+    0x0000800080204009, 0x2010004140002001, 0x9800200280100080, 0x1000100080080080,
+    0x0442000a00049020, 0x2100040080020080, 0x0800120400900148, 0x0010040a00128541,
+    0x2800804000800030, 0x1010002000400041, 0x4000200011004100, 0x0610008410800800,
+    0x0400802402800800, 0xc100020080800400, 0x0002000802000401, 0x0182085882000401,
+    0x0220204000808000, 0x2860100040024022, 0x0001002004110040, 0x99101042000a0020,
+    0x0004080004008080, 0x0010040002008080, 0x2012004881020004, 0x8300842444820011,
+    0x0088403882010200, 0x0820400080210100, 0x0110910040a00300, 0x0801100280080480,
+    0x0242009008200600, 0x1002000489500200, 0x0040800200010080, 0x0091800041000080,
+    0x0000209300488001, 0x04c1002414824001, 0x020020000b001041, 0x7000100004200901,
+    0x8002002004100802, 0x30010002084c0007, 0x0888221800813004, 0x4000002840840112,
+];
+const BISHOP_MAGICS: [u64; 64] = [
+    0xa010041108003100, 0x006082020a002900, 0x6810010619200000, 0x08281a0520000408,
+    0x0001104001000400, 0x0018901008048400, 0x00040a0210245280, 0x000200210808a402,
+    0x9140048410821200, 0x0800091010820041, 0x20504804832202c0, 0x0100091401081000,
+    0x8021011140000012, 0x0810020804450400, 0x208b0542109008a2, 0x0080084a08040204,
+    0x0040e2a80811244c, 0x2505022008008108, 0x0430220100420040, 0x010a040420220040,
+    0x1105000290400000, 0x0093001200822120, 0x4000a62048043004, 0x280120048a015004,
+    0x006090002a020814, 0x44042000240800d0, 0x01102800040a4400, 0x1004080080220040,
+    0x0001001011004024, 0x0010044000805040, 0x0914041200820100, 0x0004821012821480,
+    0x0024040500c05021, 0x0088611002080200, 0x0116080a00040020, 0x4000020080080080,
+    0x2450450140840040, 0x0000880201484100, 0x0222020404020092, 0x8081110600002e00,
+    0x2842101105000801, 0x1100809008001025, 0x00020202221c0400, 0x0422014022009020,
+    0x0210046102100c00, 0xc004008082029102, 0x00aa461801101200, 0x0404080080201108,
+    0x020542108c205002, 0x0410544804100100, 0x0040910841100000, 0x0400200042021100,
+    0x00004204850400c0, 0x0200100410a42102, 0x1040020801210102, 0x0805040410420000,
+    0x2884804130100200, 0x800c262201242000, 0x1058000194108800, 0x0014221054420204,
+    0x0104000012a02200, 0x0200881003300100, 0x0140400202840100, 0x0402020801010201,
+];
+
+#[derive(Clone, Copy)]
+struct Magic {
+    mask: Bitboard,
+    magic: u64,
+    shift: u32,
+    offset: usize,
+}
+
+impl Magic {
+    fn index(&self, occupied: Bitboard) -> usize {
+        self.offset + ((occupied & self.mask).wrapping_mul(self.magic) >> self.shift) as usize
+    }
+}
+
+struct Tables {
+    knight: [Bitboard; 64],
+    king: [Bitboard; 64],
+    pawn: [[Bitboard; 64]; 2],
+    between: [[Bitboard; 64]; 64],
+    rook: [Magic; 64],
+    bishop: [Magic; 64],
+    // the slider attacks for every square and set of blockers, found through the magics
+    attacks: Vec<Bitboard>,
+}
+
+fn tables() -> &'static Tables {
+    static TABLES: OnceLock<Tables> = OnceLock::new();
+    TABLES.get_or_init(build_tables)
+}
+
+// the square the given number of files and ranks away, None if that is off the board
+fn step(square: Square, (dir_file, dir_rank): (isize, isize)) -> Option<Square> {
+    let file = square.file() as isize + dir_file;
+    let rank = square.rank() as isize + dir_rank;
+    if file < 0 || rank < 0 {
+        return None;
+    }
+    Square::new(file as usize, rank as usize)
+}
+
+// the squares a slider on the square reaches in the directions, stopping at the first occupied
+// square it hits
+fn slide(square: Square, directions: &[(isize, isize)], occupied: Bitboard) -> Bitboard {
+    let mut attacks = 0;
+    for &direction in directions {
+        let mut cur = step(square, direction);
+        while let Some(s) = cur {
+            attacks |= bit(s);
+            if occupied & bit(s) != 0 {
+                break;
+            }
+            cur = step(s, direction);
+        }
+    }
+    attacks
+}
+
+// the squares a piece jumps to with the given steps
+fn jumps(square: Square, steps: &[(isize, isize)]) -> Bitboard {
+    steps.iter().filter_map(|&s| step(square, s)).fold(0, |attacks, cur| attacks | bit(cur))
+}
+
+fn build_magics(directions: &[(isize, isize)], magics: &[u64; 64], attacks: &mut Vec<Bitboard>) -> [Magic; 64] {
+    let mut table = [Magic { mask: 0, magic: 0, shift: 0, offset: 0 }; 64];
+    for square in Square::all() {
+        // the edge squares never block anything further, so they are left out of the mask
+        let mut edges = 0;
+        if square.file() != 0 { edges |= 0x0101_0101_0101_0101; }
+        if square.file() != 7 { edges |= 0x8080_8080_8080_8080; }
+        if square.rank() != 0 { edges |= RANK_1; }
+        if square.rank() != 7 { edges |= RANK_8; }
+        let mask = slide(square, directions, 0) & !edges;
+        let magic = Magic { mask, magic: magics[square.index()], shift: 64 - mask.count_ones(), offset: attacks.len() };
+        attacks.resize(attacks.len() + (1 << mask.count_ones()), 0);
+        // every subset of the mask
+        let mut blockers: Bitboard = 0;
+        loop {
+            attacks[magic.index(blockers)] = slide(square, directions, blockers);
+            blockers = blockers.wrapping_sub(mask) & mask;
+            if blockers == 0 {
+                break;
+            }
+        }
+        table[square.index()] = magic;
+    }
+    table
+}
+
+fn build_tables() -> Tables {
+    let knight_steps = [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+    let king_steps = [(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)];
+    let mut tables = Tables {
+        knight: [0; 64],
+        king: [0; 64],
+        pawn: [[0; 64]; 2],
+        between: [[0; 64]; 64],
+        rook: [Magic { mask: 0, magic: 0, shift: 0, offset: 0 }; 64],
+        bishop: [Magic { mask: 0, magic: 0, shift: 0, offset: 0 }; 64],
+        attacks: Vec::new(),
+    };
+    for square in Square::all() {
+        let i = square.index();
+        tables.knight[i] = jumps(square, &knight_steps);
+        tables.king[i] = jumps(square, &king_steps);
+        tables.pawn[Color::White as usize][i] = jumps(square, &[(-1, 1), (1, 1)]);
+        tables.pawn[Color::Black as usize][i] = jumps(square, &[(-1, -1), (1, -1)]);
+        for &direction in ROOK_DIRECTIONS.iter().chain(&BISHOP_DIRECTIONS) {
+            // walk the ray, everything passed on the way to a square is between the two
+            let mut passed = 0;
+            let mut cur = step(square, direction);
+            while let Some(s) = cur {
+                tables.between[i][s.index()] = passed;
+                passed |= bit(s);
+                cur = step(s, direction);
+            }
+        }
+    }
+    let mut attacks = Vec::new();
+    tables.rook = build_magics(&ROOK_DIRECTIONS, &ROOK_MAGICS, &mut attacks);
+    tables.bishop = build_magics(&BISHOP_DIRECTIONS, &BISHOP_MAGICS, &mut attacks);
+    tables.attacks = attacks;
+    tables
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn attack_tables() {
+        assert_eq!(knight_attacks(Square::A1), bit(Square::B3) | bit(Square::C2));
+        assert_eq!(king_attacks(Square::H8), bit(Square::G8) | bit(Square::G7) | bit(Square::H7));
+        assert_eq!(pawn_attacks(Color::White, Square::E4), bit(Square::D5) | bit(Square::F5));
+        assert_eq!(pawn_attacks(Color::Black, Square::A7), bit(Square::B6));
+        assert_eq!(between(Square::A1, Square::D4), bit(Square::B2) | bit(Square::C3));
+        assert_eq!(between(Square::E1, Square::H1), bit(Square::F1) | bit(Square::G1));
+        assert_eq!(between(Square::E1, Square::F3), 0);
+
+        // the magic lookups agree with walking the rays, for a spread of blockers
+        let mut occupied: Bitboard = 0x0123_4567_89ab_cdef;
+        for _ in 0..200 {
+            occupied ^= occupied << 13;
+            occupied ^= occupied >> 7;
+            occupied ^= occupied << 17;
+            for square in Square::all() {
+                assert_eq!(rook_attacks(square, occupied), slide(square, &ROOK_DIRECTIONS, occupied));
+                assert_eq!(bishop_attacks(square, occupied), slide(square, &BISHOP_DIRECTIONS, occupied));
+            }
+        }
+    }
+}
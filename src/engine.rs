@@ -0,0 +1,124 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::{Game, SearchLimits, SearchResult, Searcher};
+
+// runs searches on a thread of their own, so a program talking to a chess GUI can go on reading
+// commands, like being told to stop, while the engine thinks. the UCI and XBoard front ends in
+// src/bin are built on it
+pub struct Engine {
+    // None while a search thread has it
+    searcher: Option<Searcher>,
+    thread: Option<JoinHandle<Searcher>>,
+    // whether the last search was started as infinite
+    infinite: bool,
+    stop: Arc<AtomicBool>,
+}
+
+impl Default for Engine {
+    fn default() -> Engine {
+        Engine::new()
+    }
+}
+
+impl Engine {
+    pub fn new() -> Engine {
+        let searcher = Searcher::new();
+        let stop = searcher.stop_flag();
+        Engine { searcher: Some(searcher), thread: None, infinite: false, stop }
+    }
+
+    // starts searching the game's position in the background, stopping any search still going.
+    // `report` is called after each iteration and `done` once with the result. an `infinite`
+    // search doesn't end by itself, even when it can't go any deeper, only on stop
+    pub fn go<R, D>(&mut self, game: &Game, limits: SearchLimits, infinite: bool, report: R, done: D)
+    where
+        R: FnMut(&SearchResult) + Send + 'static,
+        D: FnOnce(Option<SearchResult>) + Send + 'static,
+    {
+        self.stop();
+        let mut searcher = self.searcher.take().unwrap();
+        self.infinite = infinite;
+        self.stop.store(false, Ordering::SeqCst);
+        let stop = self.stop.clone();
+        let game = game.clone();
+        self.thread = Some(thread::spawn(move || {
+            let result = searcher.search_with(&game, limits, report);
+            while infinite && !stop.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_millis(1));
+            }
+            done(result);
+            searcher
+        }));
+    }
+
+    // stops the search, if there is one, and waits for it to call `done`
+    pub fn stop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        self.wait();
+    }
+
+    // waits for the search to end by itself
+    pub fn wait(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            self.searcher = Some(thread.join().unwrap());
+        }
+    }
+
+    pub fn is_searching(&self) -> bool {
+        self.thread.as_ref().is_some_and(|thread| !thread.is_finished())
+    }
+
+    // the searcher, e.g. to resize its table or clear it for a new game. waits for a search to
+    // end by itself first, but an infinite search only ends on stop, so while one is running
+    // this is None
+    pub fn searcher(&mut self) -> Option<&mut Searcher> {
+        if self.infinite && self.is_searching() {
+            return None;
+        }
+        self.wait();
+        self.searcher.as_mut()
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    pub fn engine_search() {
+        let mut engine = Engine::new();
+        let game = Game::new_game();
+        let (send, receive) = mpsc::channel();
+
+        // an infinite search keeps going until it is stopped
+        let depths = send.clone();
+        engine.go(&game, SearchLimits { depth: Some(2), ..Default::default() }, true, move |result| depths.send(Some(result.depth)).unwrap(), move |result| send.send(result.map(|result| result.depth)).unwrap());
+        assert_eq!(receive.recv().unwrap(), Some(1));
+        assert_eq!(receive.recv().unwrap(), Some(2));
+        thread::sleep(Duration::from_millis(20));
+        assert!(engine.is_searching());
+        assert!(receive.try_recv().is_err());
+        // the searcher isn't handed out while an infinite search has it
+        assert!(engine.searcher().is_none());
+        engine.stop();
+        assert!(!engine.is_searching());
+        assert_eq!(receive.recv().unwrap(), Some(2));
+
+        // the searcher is handed back for the next search
+        let (send, receive) = mpsc::channel();
+        engine.go(&game, SearchLimits { depth: Some(3), ..Default::default() }, false, |_| {}, move |result| send.send(result).unwrap());
+        engine.wait();
+        assert_eq!(receive.recv().unwrap().unwrap().depth, 3);
+        engine.searcher().unwrap().clear();
+    }
+}
@@ -0,0 +1,252 @@
+use std::cmp::{max, min};
+use std::sync::OnceLock;
+
+use crate::bitboard::{bishop_attacks, bit, king_attacks, knight_attacks, rook_attacks, squares, Bitboard};
+use crate::{Color, Game, PieceType, Square};
+
+// a weight for the middlegame and one for the endgame. the evaluation slides from the first to
+// the second as pieces come off the board
+pub type Tapered = (i32, i32);
+
+// how much the phase counts for each piece type, a full board of pieces is MAX_PHASE
+const PHASE: [i32; 6] = [0, 1, 1, 2, 4, 0];
+const MAX_PHASE: i32 = 24;
+const FILE_A: Bitboard = 0x0101_0101_0101_0101;
+
+// everything evaluate weighs a position by, in centipawns. arrays are indexed by PieceType, and
+// the piece-square tables by square from white's side, mirrored for black
+#[derive(Clone, PartialEq, Debug)]
+pub struct EvalWeights {
+    pub material: [Tapered; 6],
+    pub piece_squares: [[Tapered; 64]; 6],
+    // for each square a piece attacks that isn't taken by its own side
+    pub mobility: [Tapered; 6],
+    // for each pawn beyond the first on a file
+    pub doubled_pawn: Tapered,
+    // for each pawn without pawns of its own color on the files beside it
+    pub isolated_pawn: Tapered,
+    // by rank from the pawn's own side, for pawns no enemy pawn can stop
+    pub passed_pawn: [Tapered; 8],
+    // for each pawn of its own color right in front of the king
+    pub king_shield: Tapered,
+    // for each enemy attack on the squares around the king
+    pub king_attack: Tapered,
+}
+
+impl Default for EvalWeights {
+    fn default() -> EvalWeights {
+        EvalWeights {
+            material: [(100, 120), (320, 300), (330, 320), (500, 550), (950, 1000), (0, 0)],
+            piece_squares: default_piece_squares(),
+            mobility: [(0, 0), (4, 4), (5, 5), (2, 4), (1, 2), (0, 0)],
+            doubled_pawn: (-10, -20),
+            isolated_pawn: (-10, -15),
+            passed_pawn: [(0, 0), (5, 10), (5, 15), (10, 25), (20, 45), (35, 75), (60, 120), (0, 0)],
+            king_shield: (10, 0),
+            king_attack: (-8, -2),
+        }
+    }
+}
+
+// centralized pieces, advanced pawns, a sheltered king while the queens are on and an active
+// one in the endgame
+fn default_piece_squares() -> [[Tapered; 64]; 6] {
+    let mut tables = [[(0, 0); 64]; 6];
+    for square in Square::all() {
+        let (file, rank) = (square.file() as i32, square.rank() as i32);
+        // 0 on the four center squares up to 3 on the edge
+        let center = max((2 * file - 7).abs(), (2 * rank - 7).abs()) / 2;
+        let i = square.index();
+        if (1..7).contains(&rank) {
+            let center_pawn = if (3..5).contains(&file) && (2..5).contains(&rank) { 10 } else { 0 };
+            tables[PieceType::Pawn as usize][i] = (5 * (rank - 1) + center_pawn, 10 * (rank - 1));
+        }
+        tables[PieceType::Knight as usize][i] = (15 - 12 * center, 10 - 10 * center);
+        tables[PieceType::Bishop as usize][i] = (10 - 5 * center, 10 - 5 * center);
+        tables[PieceType::Rook as usize][i] = (if rank == 6 { 20 } else { 0 }, 0);
+        tables[PieceType::Queen as usize][i] = (5 - 3 * center, 10 - 5 * center);
+        let sheltered = match rank {
+            0 if [0, 1, 2, 6, 7].contains(&file) => 20,
+            0 => 0,
+            _ => -15 * min(rank, 3),
+        };
+        tables[PieceType::King as usize][i] = (sheltered, 30 - 15 * center);
+    }
+    tables
+}
+
+// an evaluation split up by what it is made of, each term already tapered by the phase and from
+// the side to move's point of view
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Evaluation {
+    pub material: i32,
+    pub piece_squares: i32,
+    pub mobility: i32,
+    pub pawn_structure: i32,
+    pub king_safety: i32,
+    // how much of the middlegame is left, from MAX_PHASE with all pieces on the board down to 0
+    // with only kings and pawns
+    pub phase: i32,
+}
+
+impl Evaluation {
+    pub fn total(&self) -> i32 {
+        self.material + self.piece_squares + self.mobility + self.pawn_structure + self.king_safety
+    }
+}
+
+impl Game {
+    // how good the position is for the side to move, in centipawns, with the default weights
+    pub fn evaluate(&self) -> i32 {
+        static WEIGHTS: OnceLock<EvalWeights> = OnceLock::new();
+        self.evaluation(WEIGHTS.get_or_init(EvalWeights::default)).total()
+    }
+
+    // the evaluation term by term, with the given weights:
+    //
+    //     let terms = game.evaluation(&EvalWeights::default());
+    //     println!("material {}, king safety {}", terms.material, terms.king_safety);
+    pub fn evaluation(&self, weights: &EvalWeights) -> Evaluation {
+        let phase = [PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen]
+            .iter()
+            .map(|&piece| PHASE[piece as usize] * self.pieces[piece as usize].count_ones() as i32)
+            .sum::<i32>()
+            .min(MAX_PHASE);
+        let taper = |(middlegame, endgame): Tapered| (middlegame * phase + endgame * (MAX_PHASE - phase)) / MAX_PHASE;
+
+        let mut terms = [(0, 0); 5];
+        for color in [Color::White, Color::Black] {
+            let sign = if color == self.turn { 1 } else { -1 };
+            for (term, (middlegame, endgame)) in terms.iter_mut().zip(self.color_terms(color, weights)) {
+                term.0 += sign * middlegame;
+                term.1 += sign * endgame;
+            }
+        }
+        Evaluation {
+            material: taper(terms[0]),
+            piece_squares: taper(terms[1]),
+            mobility: taper(terms[2]),
+            pawn_structure: taper(terms[3]),
+            king_safety: taper(terms[4]),
+            phase,
+        }
+    }
+
+    // material, piece-square, mobility, pawn structure and king safety for one side, untapered
+    fn color_terms(&self, color: Color, weights: &EvalWeights) -> [Tapered; 5] {
+        let mut terms = [(0, 0); 5];
+        let add = |term: &mut Tapered, (middlegame, endgame): Tapered, times: i32| {
+            term.0 += middlegame * times;
+            term.1 += endgame * times;
+        };
+        let own = self.colors[color as usize];
+        let occupied = self.occupied();
+        let enemy_king_zone = self.king_square(color.opposite()).map_or(0, |king| king_attacks(king) | bit(king));
+
+        for piece in [PieceType::Pawn, PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen, PieceType::King] {
+            let p = piece as usize;
+            for square in squares(self.pieces[p] & own) {
+                add(&mut terms[0], weights.material[p], 1);
+                let table_square = if color == Color::White { square.index() } else { square.index() ^ 56 };
+                add(&mut terms[1], weights.piece_squares[p][table_square], 1);
+                let attacks = match piece {
+                    PieceType::Knight => knight_attacks(square),
+                    PieceType::Bishop => bishop_attacks(square, occupied),
+                    PieceType::Rook => rook_attacks(square, occupied),
+                    PieceType::Queen => bishop_attacks(square, occupied) | rook_attacks(square, occupied),
+                    _ => continue,
+                };
+                add(&mut terms[2], weights.mobility[p], (attacks & !own).count_ones() as i32);
+                // counted against the other side's king safety
+                add(&mut terms[4], weights.king_attack, -((attacks & enemy_king_zone).count_ones() as i32));
+            }
+        }
+
+        let pawns = self.pieces_of(PieceType::Pawn, color);
+        let enemy_pawns = self.pieces_of(PieceType::Pawn, color.opposite());
+        for file in 0..8 {
+            let on_file = (pawns & FILE_A << file).count_ones() as i32;
+            if on_file > 1 {
+                add(&mut terms[3], weights.doubled_pawn, on_file - 1);
+            }
+            if on_file > 0 && pawns & beside(file) == 0 {
+                add(&mut terms[3], weights.isolated_pawn, on_file);
+            }
+        }
+        for square in squares(pawns) {
+            let rank = square.rank();
+            // the ranks in front of the pawn, on its own file and the files beside it. from_fen
+            // allows pawns on the last rank, with nothing in front of them
+            let ahead = match color {
+                Color::White => Bitboard::MAX.checked_shl(8 * (rank as u32 + 1)).unwrap_or(0),
+                Color::Black => (1 << (8 * rank)) - 1,
+            };
+            if enemy_pawns & ahead & (FILE_A << square.file() | beside(square.file())) == 0 {
+                let relative_rank = if color == Color::White { rank } else { 7 - rank };
+                add(&mut terms[3], weights.passed_pawn[relative_rank], 1);
+            }
+        }
+
+        if let Some(king) = self.king_square(color) {
+            let forward = if color == Color::White { 1 } else { -1 };
+            let shield = squares(king_attacks(king))
+                .filter(|square| square.rank() as i32 == king.rank() as i32 + forward)
+                .fold(0, |shield, square| shield | bit(square));
+            add(&mut terms[4], weights.king_shield, (shield & pawns).count_ones() as i32);
+        }
+        terms
+    }
+}
+
+fn beside(file: usize) -> Bitboard {
+    let mut files = 0;
+    if file > 0 {
+        files |= FILE_A << (file - 1);
+    }
+    if file < 7 {
+        files |= FILE_A << (file + 1);
+    }
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn evaluate() {
+        let weights = EvalWeights::default();
+        // the starting position is the same for both sides
+        let start = Game::new_game().evaluation(&weights);
+        assert_eq!(start, Evaluation { phase: MAX_PHASE, ..Default::default() });
+
+        // a mirrored position scores the same for the side to move
+        let white = Game::game_from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4");
+        let black = Game::game_from_fen("rnbqk2r/pppp1ppp/5n2/2b1p3/4P3/2N2N2/PPPP1PPP/R1BQKB1R b KQkq - 4 4");
+        assert_eq!(white.evaluation(&weights), black.evaluation(&weights));
+
+        // an extra queen is better for the side that has it, whoever is to move
+        let terms = Game::game_from_fen("3qk3/8/8/8/8/8/8/4K3 w - - 0 1").evaluation(&weights);
+        assert_eq!(terms.phase, 4);
+        assert!(terms.material < -900 && terms.total() < -900);
+        assert!(Game::game_from_fen("3qk3/8/8/8/8/8/8/4K3 b - - 0 1").evaluate() > 900);
+
+        // doubled and isolated pawns against a healthy pawn, and a passed pawn
+        let terms = Game::game_from_fen("4k3/5p2/8/8/8/2P5/2P5/4K3 w - - 0 1").evaluation(&weights);
+        assert!(terms.pawn_structure < 0);
+        let terms = Game::game_from_fen("4k3/8/1P6/8/8/8/7p/4K3 b - - 0 1").evaluation(&weights);
+        assert!(terms.pawn_structure > 0);
+        // pawns on the last ranks, which from_fen lets through, don't break it
+        let terms = Game::game_from_fen("P3k3/8/8/8/8/8/8/4K2p w - - 0 1").evaluation(&weights);
+        assert_eq!(terms.pawn_structure, 0);
+
+        // an open king under attack
+        let sheltered = Game::game_from_fen("r4rk1/5ppp/8/8/8/8/5PPP/R4RK1 w - - 0 1").evaluation(&weights);
+        let exposed = Game::game_from_fen("r4rk1/5ppp/8/8/8/8/8/R4RK1 w - - 0 1").evaluation(&weights);
+        assert!(exposed.king_safety < sheltered.king_safety);
+
+        // the weights can be changed
+        let no_material = EvalWeights { material: [(0, 0); 6], ..EvalWeights::default() };
+        assert_eq!(Game::game_from_fen("3qk3/8/8/8/8/8/8/4K3 w - - 0 1").evaluation(&no_material).material, 0);
+    }
+}
@@ -0,0 +1,159 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use crate::{Decision, Game, Move, Piece, Termination};
+
+// what happened when a move was played, returned by do_move
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct MoveOutcome {
+    // for en passant this is the pawn beside the start square
+    pub captured: Option<Piece>,
+    // whether the side to move is now in check, also true for checkmate
+    pub check: bool,
+    // set when the move ended the game
+    pub termination: Option<Termination>,
+    pub decision: Option<Decision>,
+}
+
+impl MoveOutcome {
+    pub fn is_checkmate(&self) -> bool {
+        self.termination == Some(Termination::Checkmate)
+    }
+
+    pub fn is_stalemate(&self) -> bool {
+        self.termination == Some(Termination::Stalemate)
+    }
+}
+
+// gets told about everything happening in a game it is added to with Game::add_listener, e.g. to
+// play a sound on captures. every method does nothing unless it is implemented
+pub trait GameListener {
+    // after every move, including redone ones, with the game as it is after the move
+    fn on_move(&mut self, _game: &Game, _mv: &Move, _outcome: &MoveOutcome) {}
+    fn on_capture(&mut self, _game: &Game, _captured: Piece) {}
+    // the side to move has been put in check, but not mated
+    fn on_check(&mut self, _game: &Game) {}
+    // the game ended, either from a move or from a claimed draw
+    fn on_game_end(&mut self, _game: &Game, _termination: Termination, _decision: Decision) {}
+}
+
+// the listeners added to a game. they aren't copied when the game is cloned, so trying moves out
+// on a copy doesn't report anything, and they don't count when comparing games
+#[derive(Default)]
+pub(crate) struct Listeners(Vec<Arc<Mutex<dyn GameListener + Send>>>);
+
+impl Listeners {
+    pub(crate) fn push(&mut self, listener: Arc<Mutex<dyn GameListener + Send>>) {
+        self.0.push(listener);
+    }
+
+    pub(crate) fn notify(&self, event: impl Fn(&mut dyn GameListener)) {
+        for listener in &self.0 {
+            // a listener that panicked earlier is skipped rather than taking the game down too
+            if let Ok(mut listener) = listener.lock() {
+                event(&mut *listener);
+            }
+        }
+    }
+}
+
+impl Clone for Listeners {
+    fn clone(&self) -> Listeners {
+        Listeners::default()
+    }
+}
+
+impl PartialEq for Listeners {
+    fn eq(&self, _other: &Listeners) -> bool {
+        true
+    }
+}
+
+impl fmt::Debug for Listeners {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} listeners", self.0.len())
+    }
+}
+
+impl Game {
+    pub fn add_listener(&mut self, listener: Arc<Mutex<dyn GameListener + Send>>) {
+        self.listeners.push(listener);
+    }
+
+    // tells the listeners about a move that was just played
+    pub(crate) fn report_move(&self, mv: &Move, outcome: &MoveOutcome) {
+        self.listeners.notify(|listener| {
+            listener.on_move(self, mv, outcome);
+            if let Some(captured) = outcome.captured {
+                listener.on_capture(self, captured);
+            }
+            if outcome.check && !outcome.is_checkmate() {
+                listener.on_check(self);
+            }
+        });
+        if let (Some(termination), Some(decision)) = (outcome.termination, outcome.decision) {
+            self.report_game_end(termination, decision);
+        }
+    }
+
+    pub(crate) fn report_game_end(&self, termination: Termination, decision: Decision) {
+        self.listeners.notify(|listener| listener.on_game_end(self, termination, decision));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Color, PieceType, Square};
+
+    #[derive(Default)]
+    struct Log(Vec<String>);
+
+    impl GameListener for Log {
+        fn on_move(&mut self, _game: &Game, mv: &Move, _outcome: &MoveOutcome) {
+            self.0.push(mv.to_string());
+        }
+        fn on_capture(&mut self, _game: &Game, captured: Piece) {
+            self.0.push(format!("takes {:?}", captured.piece));
+        }
+        fn on_check(&mut self, game: &Game) {
+            self.0.push(format!("{:?} in check", game.turn));
+        }
+        fn on_game_end(&mut self, _game: &Game, termination: Termination, decision: Decision) {
+            self.0.push(format!("{:?} {:?}", termination, decision));
+        }
+    }
+
+    #[test]
+    pub fn move_outcomes() {
+        let log = Arc::new(Mutex::new(Log::default()));
+        let mut game = Game::game_from_fen("6k1/5ppp/8/8/8/8/r7/R3K3 w - - 0 1");
+        game.add_listener(log.clone());
+        let outcome = game.do_move(Move::new(Square::A1, Square::A2)).unwrap();
+        assert_eq!(outcome, MoveOutcome {
+            captured: Some(Piece{piece: PieceType::Rook, color: Color::Black}),
+            check: false,
+            termination: None,
+            decision: None,
+        });
+        game.do_move(Move::new(Square::H7, Square::H6)).unwrap();
+        // a copy of the game doesn't report to the listeners
+        game.clone().do_move(Move::new(Square::E1, Square::D1)).unwrap();
+        let outcome = game.do_move(Move::new(Square::A2, Square::A8)).unwrap();
+        assert!(outcome.check && !outcome.is_checkmate());
+
+        let mut fools_mate = Game::new_game();
+        fools_mate.add_listener(log.clone());
+        for mv in [(Square::F2, Square::F3), (Square::E7, Square::E5), (Square::G2, Square::G4)] {
+            fools_mate.do_move(Move::new(mv.0, mv.1)).unwrap();
+        }
+        let outcome = fools_mate.do_move(Move::new(Square::D8, Square::H4)).unwrap();
+        assert!(outcome.is_checkmate() && outcome.check);
+        assert_eq!(outcome.decision, Some(Decision::Black));
+
+        assert_eq!(log.lock().unwrap().0, [
+            "a1a2", "takes Rook", "h7h6", "a2a8", "Black in check",
+            "f2f3", "e7e5", "g2g4", "d8h4", "Checkmate Black",
+        ]);
+    }
+}
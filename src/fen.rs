@@ -0,0 +1,324 @@
+use std::fmt;
+
+use crate::{Color, Game, Piece, PieceType, Square};
+
+// the six space separated fields of a FEN string
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FenField {
+    Placement,
+    SideToMove,
+    Castling,
+    EnPassant,
+    HalfmoveClock,
+    FullmoveNumber,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FenErrorKind {
+    MissingField,
+    TooManyFields,
+    InvalidCharacter(char),
+    RepeatedCharacter(char),
+    RankTooLong,
+    RankTooShort,
+    WrongNumberOfRanks,
+    InvalidSquare,
+    InvalidNumber,
+}
+
+// what went wrong, in which field, and at which character of the FEN string (counting from 0)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FenError {
+    pub field: FenField,
+    pub position: usize,
+    pub kind: FenErrorKind,
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let field = match self.field {
+            FenField::Placement => "piece placement",
+            FenField::SideToMove => "side to move",
+            FenField::Castling => "castling",
+            FenField::EnPassant => "en passant",
+            FenField::HalfmoveClock => "halfmove clock",
+            FenField::FullmoveNumber => "fullmove number",
+        };
+        match self.kind {
+            FenErrorKind::MissingField => write!(f, "missing {} field", field)?,
+            FenErrorKind::TooManyFields => write!(f, "unexpected field after the {} field", field)?,
+            FenErrorKind::InvalidCharacter(c) => write!(f, "invalid character '{}' in the {} field", c, field)?,
+            FenErrorKind::RepeatedCharacter(c) => write!(f, "repeated character '{}' in the {} field", c, field)?,
+            FenErrorKind::RankTooLong => write!(f, "more than 8 squares in a rank")?,
+            FenErrorKind::RankTooShort => write!(f, "less than 8 squares in a rank")?,
+            FenErrorKind::WrongNumberOfRanks => write!(f, "the board doesn't have 8 ranks")?,
+            FenErrorKind::InvalidSquare => write!(f, "invalid {} square", field)?,
+            FenErrorKind::InvalidNumber => write!(f, "invalid {}", field)?,
+        }
+        write!(f, " at position {}", self.position)
+    }
+}
+
+impl std::error::Error for FenError {}
+
+impl Game {
+    // reads a FEN string, checking every field. the two clock fields may be left out, and
+    // then default to "0 1"
+    pub fn from_fen(s: &str) -> Result<Game, FenError> {
+        let fields = split_fields(s);
+        let order = [
+            FenField::Placement,
+            FenField::SideToMove,
+            FenField::Castling,
+            FenField::EnPassant,
+            FenField::HalfmoveClock,
+            FenField::FullmoveNumber,
+        ];
+        if fields.len() > order.len() {
+            return Err(FenError { field: FenField::FullmoveNumber, position: fields[order.len()].0, kind: FenErrorKind::TooManyFields });
+        }
+        if fields.len() < 4 {
+            return Err(FenError { field: order[fields.len()], position: s.chars().count(), kind: FenErrorKind::MissingField });
+        }
+
+        let mut g = Game::empty_game();
+        read_placement(&mut g, fields[0])?;
+
+        let (start, side) = fields[1];
+        g.turn = match side {
+            "w" => Color::White,
+            "b" => Color::Black,
+            _ => {
+                // point at the first character that makes it neither "w" nor "b"
+                let (i, c) = side.chars().enumerate().find(|&(i, c)| i > 0 || (c != 'w' && c != 'b')).unwrap();
+                return Err(FenError { field: FenField::SideToMove, position: start + i, kind: FenErrorKind::InvalidCharacter(c) });
+            }
+        };
+
+        let (start, castling) = fields[2];
+        if castling != "-" {
+            for (i, c) in castling.chars().enumerate() {
+                let right = match c {
+                    'K' => &mut g.castling.white_king_side,
+                    'Q' => &mut g.castling.white_queen_side,
+                    'k' => &mut g.castling.black_king_side,
+                    'q' => &mut g.castling.black_queen_side,
+                    _ => return Err(FenError { field: FenField::Castling, position: start + i, kind: FenErrorKind::InvalidCharacter(c) }),
+                };
+                if *right {
+                    return Err(FenError { field: FenField::Castling, position: start + i, kind: FenErrorKind::RepeatedCharacter(c) });
+                }
+                *right = true;
+            }
+        }
+
+        let (start, en_passant) = fields[3];
+        if en_passant != "-" {
+            // the skipped square is on the 6th rank when white is to move, and the 3rd for black
+            let rank = match g.turn {
+                Color::White => 5,
+                Color::Black => 2,
+            };
+            match en_passant.parse::<Square>() {
+                Ok(square) if square.rank() == rank => g.en_passant = Some(square),
+                _ => return Err(FenError { field: FenField::EnPassant, position: start, kind: FenErrorKind::InvalidSquare }),
+            }
+        }
+
+        if let Some(&(start, clock)) = fields.get(4) {
+            g.halfmove_clock = clock.parse().map_err(|_| FenError { field: FenField::HalfmoveClock, position: start, kind: FenErrorKind::InvalidNumber })?;
+        }
+        if let Some(&(start, number)) = fields.get(5) {
+            g.fullmove_number = match number.parse() {
+                Ok(n) if n > 0 => n,
+                _ => return Err(FenError { field: FenField::FullmoveNumber, position: start, kind: FenErrorKind::InvalidNumber }),
+            };
+        }
+        // i guess move history can be ignored for this
+        Ok(g)
+    }
+
+    // like from_fen, but panics with the FenError when the FEN string is invalid, where it used
+    // to load whatever it could make of it. meant for FEN strings known to be good, like ones
+    // written in the code; use from_fen for anything coming from a user or a file
+    pub fn game_from_fen(s: &str) -> Game {
+        Game::from_fen(s).unwrap_or_else(|e| panic!("invalid FEN: {}", e))
+    }
+
+    // writes the position as a FEN string, the inverse of game_from_fen
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+            for file in 0..8 {
+                match self.piece_at(Square::new(file, rank).unwrap()) {
+                    None => empty += 1,
+                    Some(p) => {
+                        if empty > 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        fen.push(piece_to_char(p));
+                    }
+                }
+            }
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+            if rank > 0 {
+                fen.push('/');
+            }
+        }
+        fen.push_str(match self.turn {
+            Color::White => " w ",
+            Color::Black => " b ",
+        });
+        let castling: String = [
+            (self.castling.white_king_side, 'K'),
+            (self.castling.white_queen_side, 'Q'),
+            (self.castling.black_king_side, 'k'),
+            (self.castling.black_queen_side, 'q'),
+        ].iter().filter(|(allowed, _)| *allowed).map(|(_, c)| *c).collect();
+        fen.push_str(if castling.is_empty() { "-" } else { &castling });
+        fen.push(' ');
+        match self.en_passant {
+            Some(square) => fen.push_str(&square.to_string()),
+            None => fen.push('-'),
+        }
+        fen.push_str(&format!(" {} {}", self.halfmove_clock, self.fullmove_number));
+        fen
+    }
+}
+
+// the fields of the string together with the character position where each one starts
+fn split_fields(s: &str) -> Vec<(usize, &str)> {
+    let mut fields = Vec::new();
+    let mut start: Option<(usize, usize)> = None;
+    for (pos, (byte, c)) in s.char_indices().enumerate() {
+        match (c.is_whitespace(), start) {
+            (true, Some((pos_start, byte_start))) => {
+                fields.push((pos_start, &s[byte_start..byte]));
+                start = None;
+            }
+            (false, None) => start = Some((pos, byte)),
+            _ => {}
+        }
+    }
+    if let Some((pos_start, byte_start)) = start {
+        fields.push((pos_start, &s[byte_start..]));
+    }
+    fields
+}
+
+fn read_placement(g: &mut Game, (start, placement): (usize, &str)) -> Result<(), FenError> {
+    let error = |position, kind| FenError { field: FenField::Placement, position, kind };
+    let mut row: usize = 7;
+    // squares filled so far in the current rank, going from the a-file towards the h-file
+    let mut files: usize = 0;
+    for (i, x) in placement.chars().enumerate() {
+        let position = start + i;
+        match x {
+            '/' => {
+                if files < 8 {
+                    return Err(error(position, FenErrorKind::RankTooShort));
+                }
+                if row == 0 {
+                    return Err(error(position, FenErrorKind::WrongNumberOfRanks));
+                }
+                row -= 1;
+                files = 0;
+            }
+            '1'..='8' => {
+                files += x as usize - '0' as usize;
+                if files > 8 {
+                    return Err(error(position, FenErrorKind::RankTooLong));
+                }
+            }
+            _ => {
+                let p = piece_from_char(x).ok_or(error(position, FenErrorKind::InvalidCharacter(x)))?;
+                if files >= 8 {
+                    return Err(error(position, FenErrorKind::RankTooLong));
+                }
+                g.set_piece(Square::new(files, row).unwrap(), Some(p));
+                files += 1;
+            }
+        }
+    }
+    let end = start + placement.chars().count();
+    if files < 8 {
+        return Err(error(end, FenErrorKind::RankTooShort));
+    }
+    if row != 0 {
+        return Err(error(end, FenErrorKind::WrongNumberOfRanks));
+    }
+    Ok(())
+}
+
+// the piece for a FEN letter, upper case for white
+pub(crate) fn piece_from_char(c: char) -> Option<Piece> {
+    let piece = match c.to_ascii_lowercase() {
+        'p' => PieceType::Pawn,
+        'n' => PieceType::Knight,
+        'b' => PieceType::Bishop,
+        'r' => PieceType::Rook,
+        'q' => PieceType::Queen,
+        'k' => PieceType::King,
+        _ => return None,
+    };
+    let color = if c.is_ascii_uppercase() { Color::White } else { Color::Black };
+    Some(Piece { piece, color })
+}
+
+// the FEN letter of a piece, upper case for white
+pub(crate) fn piece_to_char(p: Piece) -> char {
+    let c = match p.piece {
+        PieceType::Pawn => 'p',
+        PieceType::Knight => 'n',
+        PieceType::Bishop => 'b',
+        PieceType::Rook => 'r',
+        PieceType::Queen => 'q',
+        PieceType::King => 'k',
+    };
+    match p.color {
+        Color::White => c.to_ascii_uppercase(),
+        Color::Black => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fen_error(s: &str) -> (FenField, usize, FenErrorKind) {
+        let e = Game::from_fen(s).unwrap_err();
+        (e.field, e.position, e.kind)
+    }
+
+    #[test]
+    pub fn bad_placement() {
+        assert_eq!(fen_error("rnbqkbnrr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), (FenField::Placement, 8, FenErrorKind::RankTooLong));
+        assert_eq!(fen_error("rnbqkbnr/ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), (FenField::Placement, 16, FenErrorKind::RankTooShort));
+        assert_eq!(fen_error("rnbqkbnr/pppppppp/8/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), (FenField::Placement, 36, FenErrorKind::WrongNumberOfRanks));
+        assert_eq!(fen_error("rnbqkbnr/pppppppp/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), (FenField::Placement, 41, FenErrorKind::WrongNumberOfRanks));
+        assert_eq!(fen_error("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), (FenField::Placement, 18, FenErrorKind::InvalidCharacter('9')));
+        assert_eq!(fen_error("rnbqkbnr/pppppppp/8/8/3x4/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), (FenField::Placement, 23, FenErrorKind::InvalidCharacter('x')));
+    }
+
+    #[test]
+    pub fn bad_fields() {
+        assert_eq!(fen_error("8/8/8/8/8/8/8/8 x - - 0 1"), (FenField::SideToMove, 16, FenErrorKind::InvalidCharacter('x')));
+        assert_eq!(fen_error("8/8/8/8/8/8/8/8 w KQkx - 0 1"), (FenField::Castling, 21, FenErrorKind::InvalidCharacter('x')));
+        assert_eq!(fen_error("8/8/8/8/8/8/8/8 w KK - 0 1"), (FenField::Castling, 19, FenErrorKind::RepeatedCharacter('K')));
+        assert_eq!(fen_error("8/8/8/8/8/8/8/8 w - e3 0 1"), (FenField::EnPassant, 20, FenErrorKind::InvalidSquare));
+        assert_eq!(fen_error("8/8/8/8/8/8/8/8 w - - -1 1"), (FenField::HalfmoveClock, 22, FenErrorKind::InvalidNumber));
+        assert_eq!(fen_error("8/8/8/8/8/8/8/8 w - - 0 0"), (FenField::FullmoveNumber, 24, FenErrorKind::InvalidNumber));
+        assert_eq!(fen_error("8/8/8/8/8/8/8/8 w"), (FenField::Castling, 17, FenErrorKind::MissingField));
+        assert_eq!(fen_error("8/8/8/8/8/8/8/8 w - - 0 1 x"), (FenField::FullmoveNumber, 26, FenErrorKind::TooManyFields));
+    }
+
+    #[test]
+    pub fn optional_clocks() {
+        let g = Game::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3").unwrap();
+        assert_eq!(g.to_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+    }
+}
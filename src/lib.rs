@@ -1,8 +1,38 @@
 use std::result;
 use std::cmp::min;
 use std::cmp::max;
+use std::ops::Index;
 
-#[derive(Clone, Copy, PartialEq, Debug)]
+use bitboard::{between, bishop_attacks, bit, king_attacks, knight_attacks, pawn_attacks, rook_attacks, squares, Bitboard, LIGHT_SQUARES, RANK_1, RANK_8};
+use events::Listeners;
+use zobrist::piece_key;
+
+mod bitboard;
+mod engine;
+mod eval;
+mod events;
+mod fen;
+mod perft;
+mod pgn;
+mod san;
+mod search;
+mod square;
+mod uci;
+mod validate;
+mod zobrist;
+
+pub use engine::Engine;
+pub use eval::{EvalWeights, Evaluation, Tapered};
+pub use events::{GameListener, MoveOutcome};
+pub use fen::{FenError, FenErrorKind, FenField};
+pub use pgn::{PgnError, PgnErrorKind, PgnGame, PgnOptions, PgnReader};
+pub use san::SanError;
+pub use search::{Score, SearchLimits, SearchResult, Searcher};
+pub use square::{ParseSquareError, Square};
+pub use uci::{CastlingNotation, UciMoveError};
+pub use validate::PositionError;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum PieceType {
     Pawn, 
     Knight,
@@ -12,6 +42,20 @@ pub enum PieceType {
     King,
 }
 
+impl PieceType {
+    // the usual value of the piece in centipawns, the king counting for nothing since it can't be
+    // traded. the evaluation has its own, configurable values in EvalWeights
+    pub fn value(self) -> i32 {
+        match self {
+            PieceType::Pawn => 100,
+            PieceType::Knight => 320,
+            PieceType::Bishop => 330,
+            PieceType::Rook => 500,
+            PieceType::Queen => 900,
+            PieceType::King => 0,
+        }
+    }
+}
 
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum Color { 
@@ -19,7 +63,16 @@ pub enum Color {
     White,
 }
 
-#[derive(Clone, Copy, PartialEq, Debug)]
+impl Color {
+    pub fn opposite(self) -> Color {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub struct Piece {
     pub piece: PieceType, 
     pub color: Color, 
@@ -32,6 +85,20 @@ pub enum Decision {
     Tie,
 }
 
+// why a game ended
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    // claimed with claim_draw
+    FiftyMoveRule,
+    SeventyFiveMoveRule,
+    // claimed with claim_draw
+    ThreefoldRepetition,
+    FivefoldRepetition,
+    InsufficientMaterial,
+}
+
 /*
 pub struct MoveType { 
     normal, 
@@ -43,22 +110,38 @@ pub struct MoveType {
 
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub struct Move {
-    pub start_x: usize,
-    pub start_y: usize,
-    pub end_x: usize,
-    pub end_y: usize,
+    pub from: Square,
+    pub to: Square,
+    // what a pawn reaching the last rank turns into, must be None for every other move
+    pub promotion: Option<PieceType>,
+}
+
+impl Move {
+    pub fn new(from: Square, to: Square) -> Move {
+        Move { from, to, promotion: None }
+    }
+    pub fn promote(from: Square, to: Square, piece: PieceType) -> Move {
+        Move { from, to, promotion: Some(piece) }
+    }
 }
 
 
-#[derive(PartialEq, Eq, Clone, Debug)]
-pub enum MoveType {
-    CaptureOrPawn, 
-    Other, 
+// a played move, with everything needed to take it back again
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct MoveRecord {
+    mv: Move,
+    piece: Piece,
+    // for en passant this is the pawn beside the start square
+    captured: Option<Piece>,
+    // the state from before the move
+    castling: CastlingRights,
+    en_passant: Option<Square>,
+    halfmove_clock: u32,
+    fullmove_number: u32,
 }
 
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub enum MoveError {
-    OutsideBoard,
     WrongColorPiece,
     FriendlyFire,
     NoPiece, 
@@ -66,83 +149,491 @@ pub enum MoveError {
     SelfCheck,
     Movement,
     Mated,
+    CastleThroughCheck,
+    MissingPromotion,
+    InvalidPromotion,
     // pinnedPiece,
 }
 
+// which castling moves are still allowed, lost once the king or the rook has moved
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CastlingRights {
+    pub white_king_side: bool,
+    pub white_queen_side: bool,
+    pub black_king_side: bool,
+    pub black_queen_side: bool,
+}
+
+impl CastlingRights {
+    pub fn all() -> CastlingRights {
+        CastlingRights {
+            white_king_side: true,
+            white_queen_side: true,
+            black_king_side: true,
+            black_queen_side: true,
+        }
+    }
+    pub fn none() -> CastlingRights {
+        CastlingRights {
+            white_king_side: false,
+            white_queen_side: false,
+            black_king_side: false,
+            black_queen_side: false,
+        }
+    }
+}
+
+// the piece on a square, the same as piece_at
+impl Index<Square> for Game {
+    type Output = Option<Piece>;
+
+    fn index(&self, square: Square) -> &Option<Piece> {
+        &self.squares[square.index()]
+    }
+}
+
+// the piece at (x, y) in the old board layout, see Board. game[(x, y)] reads what the board field
+// used to hold at board[y][x], and panics outside the board the same way
+impl Index<(usize, usize)> for Game {
+    type Output = Option<Piece>;
+
+    fn index(&self, (x, y): (usize, usize)) -> &Option<Piece> {
+        assert!(x < 8 && y < 8, "({}, {}) is outside the board", x, y);
+        &self.squares[8 * y + 7 - x]
+    }
+}
+
+// the board as an array indexed [y][x], with the files mirrored: x = 7 is the a-file and x = 0 the
+// h-file, y = 0 is the first rank
+pub type Board = [[Option<Piece>; 8]; 8];
+
 #[derive(Clone, PartialEq, Debug)]
 pub struct Game {
-    pub board: [[Option<Piece>; 8]; 8],
     pub turn: Color, 
+    pub castling: CastlingRights,
+    pub en_passant: Option<Square>,
+    // half moves since the last capture or pawn move, and the move number, as in FEN
+    pub halfmove_clock: u32,
+    pub fullmove_number: u32,
+    // one bitboard per piece type and one per color, indexed with `as usize`
+    pieces: [Bitboard; 6],
+    colors: [Bitboard; 2],
+    // the same pieces by square, for looking up what stands where
+    squares: [Option<Piece>; 64],
+    // the zobrist keys of every piece on the board xored together, see hash_key
+    piece_key: u64,
     finished: bool, 
-    move_history: Vec<MoveType>, /* will be needed to check whether draw can be claimed */
+    termination: Option<Termination>,
+    move_history: Vec<MoveRecord>,
+    // the repetition_key from before each move in move_history, for the repetition rules
+    positions: Vec<u64>,
+    // moves taken back by undo_move, the next one to redo last
+    undone_moves: Vec<Move>,
+    listeners: Listeners,
 }
 
 impl Game {
     pub fn new_game() -> Game {
-        Game {
-            board: {
-                [[
-                    Some(Piece{piece: PieceType::Rook, color: Color::White}),  
-                    Some(Piece{piece: PieceType::Knight, color: Color::White}), 
-                    Some(Piece{piece: PieceType::Bishop, color: Color::White}), 
-                    Some(Piece{piece: PieceType::King, color: Color::White}), 
-                    Some(Piece{piece: PieceType::Queen, color: Color::White}), 
-                    Some(Piece{piece: PieceType::Bishop, color: Color::White}), 
-                    Some(Piece{piece: PieceType::Knight, color: Color::White}), 
-                    Some(Piece{piece: PieceType::Rook, color: Color::White}) 
-                ],
-                [Some(Piece{piece: PieceType::Pawn, color: Color::White}); 8],
-                [None; 8],
-                [None; 8],
-                [None; 8],
-                [None; 8],
-                [Some(Piece{piece: PieceType::Pawn, color: Color::Black}); 8],
-                [
-                    Some(Piece{piece: PieceType::Rook, color: Color::Black}),  
-                    Some(Piece{piece: PieceType::Knight, color: Color::Black}), 
-                    Some(Piece{piece: PieceType::Bishop, color: Color::Black}), 
-                    Some(Piece{piece: PieceType::King, color: Color::Black}), 
-                    Some(Piece{piece: PieceType::Queen, color: Color::Black}), 
-                    Some(Piece{piece: PieceType::Bishop, color: Color::Black}), 
-                    Some(Piece{piece: PieceType::Knight, color: Color::Black}), 
-                    Some(Piece{piece: PieceType::Rook, color: Color::Black}) 
-                ]]
-            }, 
-
-            turn: Color::White,
-            move_history: Vec::new(),
-            finished: false,
+        let mut game = Game::empty_game();
+        let back_rank = [
+            PieceType::Rook,
+            PieceType::Knight,
+            PieceType::Bishop,
+            PieceType::Queen,
+            PieceType::King,
+            PieceType::Bishop,
+            PieceType::Knight,
+            PieceType::Rook,
+        ];
+        for (file, &piece) in back_rank.iter().enumerate() {
+            game.set_piece(Square::new(file, 0).unwrap(), Some(Piece{piece, color: Color::White}));
+            game.set_piece(Square::new(file, 1).unwrap(), Some(Piece{piece: PieceType::Pawn, color: Color::White}));
+            game.set_piece(Square::new(file, 6).unwrap(), Some(Piece{piece: PieceType::Pawn, color: Color::Black}));
+            game.set_piece(Square::new(file, 7).unwrap(), Some(Piece{piece, color: Color::Black}));
         }
+        game.castling = CastlingRights::all();
+        game
     }
     pub fn empty_game() -> Game {
         Game {
-            board: {
-                [[None; 8]; 8]
-            },
             turn: Color::White,
+            castling: CastlingRights::none(),
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            pieces: [0; 6],
+            colors: [0; 2],
+            squares: [None; 64],
+            piece_key: 0,
             finished: false,
+            termination: None,
             move_history: Vec::new(),
+            positions: Vec::new(),
+            undone_moves: Vec::new(),
+            listeners: Listeners::default(),
+        }
+    }
+
+    pub fn piece_at(&self, square: Square) -> Option<Piece> {
+        self.squares[square.index()]
+    }
+
+    // puts a piece on the square, or clears it with None, e.g. for setting up a position on an
+    // empty_game. nothing is checked, use validate() afterwards
+    pub fn set_piece(&mut self, square: Square, piece: Option<Piece>) {
+        if let Some(old) = self.squares[square.index()] {
+            self.pieces[old.piece as usize] &= !bit(square);
+            self.colors[old.color as usize] &= !bit(square);
+            self.piece_key ^= piece_key(old, square);
+        }
+        if let Some(new) = piece {
+            self.pieces[new.piece as usize] |= bit(square);
+            self.colors[new.color as usize] |= bit(square);
+            self.piece_key ^= piece_key(new, square);
+        }
+        self.squares[square.index()] = piece;
+    }
+
+    // the whole board as an array, see Board for the layout
+    pub fn board(&self) -> Board {
+        let mut board = [[None; 8]; 8];
+        for square in Square::all() {
+            board[square.y()][square.x()] = self.piece_at(square);
+        }
+        board
+    }
+
+    pub(crate) fn occupied(&self) -> Bitboard {
+        self.colors[0] | self.colors[1]
+    }
+
+    // the squares holding `color`'s pieces of the kind
+    pub(crate) fn pieces_of(&self, piece: PieceType, color: Color) -> Bitboard {
+        self.pieces[piece as usize] & self.colors[color as usize]
+    }
+
+    pub(crate) fn king_square(&self, color: Color) -> Option<Square> {
+        squares(self.pieces_of(PieceType::King, color)).next()
+    }
+
+    // how the game ended, None while it is still going
+    pub fn termination(&self) -> Option<Termination> {
+        self.termination
+    }
+
+    // who won, None while the game is still going
+    pub fn decision(&self) -> Option<Decision> {
+        match self.termination? {
+            // the side to move is the one that got mated
+            Termination::Checkmate => Some(match self.turn {
+                Color::White => Decision::Black,
+                Color::Black => Decision::White,
+            }),
+            _ => Some(Decision::Tie),
         }
     }
+
+    // after 50 moves by each side without a capture or pawn move, either player may claim a draw
+    pub fn can_claim_fifty_move_draw(&self) -> bool {
+        !self.finished && self.halfmove_clock >= 100
+    }
+
+    // a draw may be claimed once the same position has come up three times
+    pub fn can_claim_threefold(&self) -> bool {
+        !self.finished && self.repetitions() >= 3
+    }
+
+    // ends the game in a draw if the side to move is allowed to claim one
+    pub fn claim_draw(&mut self) -> Option<Decision> {
+        if self.can_claim_threefold() {
+            self.termination = Some(Termination::ThreefoldRepetition);
+        }
+        else if self.can_claim_fifty_move_draw() {
+            self.termination = Some(Termination::FiftyMoveRule);
+        }
+        else {
+            return None;
+        }
+        self.finished = true;
+        // like a new move, the claim throws away the moves that could have been redone
+        self.undone_moves.clear();
+        self.report_game_end(self.termination.unwrap(), Decision::Tie);
+        Some(Decision::Tie)
+    }
+
+    // true if `color` can't checkmate whatever the other side does, e.g. a lone king or a king and
+    // a single knight. a side running out of time against this is a draw rather than a loss
+    pub fn is_insufficient_material(&self, color: Color) -> bool {
+        let other = color.opposite();
+        if self.pieces_of(PieceType::Pawn, color) | self.pieces_of(PieceType::Rook, color) | self.pieces_of(PieceType::Queen, color) != 0 {
+            return false;
+        }
+        let count = |piece: PieceType, color: Color| self.pieces_of(piece, color).count_ones();
+        // bishops on light and dark squares
+        let bishops_on = |color: Color| {
+            let bishops = self.pieces_of(PieceType::Bishop, color);
+            [(bishops & LIGHT_SQUARES).count_ones(), (bishops & !LIGHT_SQUARES).count_ones()]
+        };
+        let knights = count(PieceType::Knight, color);
+        let bishops = bishops_on(color);
+        let opponent_knights = count(PieceType::Knight, other);
+        let opponent_bishops = bishops_on(other);
+        let opponent_pawns = count(PieceType::Pawn, other);
+        let opponent_others = count(PieceType::Rook, other);
+        match (knights, bishops) {
+            (0, [0, 0]) => true,
+            // a lone knight needs the other side's pieces to block its king in
+            (1, [0, 0]) => opponent_knights + opponent_bishops[0] + opponent_bishops[1] + opponent_pawns + opponent_others == 0,
+            // bishops on a single color need something on the other color to help them
+            (0, [_, 0]) => opponent_knights + opponent_pawns + opponent_bishops[1] == 0,
+            (0, [0, _]) => opponent_knights + opponent_pawns + opponent_bishops[0] == 0,
+            _ => false,
+        }
+    }
+
+    // how many times the current position has come up, counting this time
+    fn repetitions(&self) -> usize {
+        let key = self.repetition_key();
+        // a capture or pawn move can never be undone, so only look back to the last one
+        let reversible = min(self.halfmove_clock as usize, self.move_history.len());
+        1 + self.positions[self.positions.len() - reversible..]
+            .iter()
+            .filter(|&&position| position == key)
+            .count()
+    }
+
     /* should perform a move if possible */
-    pub fn do_move(&mut self, mv: Move) -> result::Result<Option<Decision>, MoveError> {
+    pub fn do_move(&mut self, mv: Move) -> result::Result<MoveOutcome, MoveError> {
+        let outcome = self.play_move(mv)?;
+        // a new move replaces whatever could have been redone
+        self.undone_moves.clear();
+        Ok(outcome)
+    }
 
-        // if found enemy pieces, means king is still checked, and must undo move
+    // takes back the last move, restoring the game exactly as it was before it. returns the move,
+    // or None if there is nothing to take back
+    pub fn undo_move(&mut self) -> Option<Move> {
+        let record = self.move_history.pop()?;
+        self.positions.pop();
+        self.unmake_move(&record);
+        debug_assert_eq!(self.hash_key(), self.compute_hash_key());
+        self.finished = false;
+        self.termination = None;
+        self.undone_moves.push(record.mv);
+        Some(record.mv)
+    }
 
-        /*
-        if !legal_Movement(mv.clone()) {
-           // return some error  
+    // plays the last move taken back by undo_move again, None if there is nothing to redo or the
+    // game has ended since
+    pub fn redo_move(&mut self) -> Option<Move> {
+        let mv = *self.undone_moves.last()?;
+        self.play_move(mv).ok()?;
+        self.undone_moves.pop();
+        Some(mv)
+    }
+
+    // plays a legal move on the board, updating everything but the game's history and result, and
+    // returns what unmake_move needs to take it back. used by play_move and by searches that try
+    // many moves
+    pub(crate) fn make_move(&mut self, mv: Move) -> MoveRecord {
+        let the_piece = self.piece_at(mv.from).unwrap();
+        let end_square = self.piece_at(mv.to);
+        let mut record = MoveRecord {
+            mv,
+            piece: the_piece,
+            captured: end_square,
+            castling: self.castling,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+        };
+        if Game::is_en_passant(&mv, &Some(the_piece), &end_square) {
+            record.captured = self.piece_at(en_passant_capture(&mv));
         }
-        */
+
+        self.move_pieces(&mv, the_piece);
+        self.update_castling_rights(&mv, the_piece);
+        // a pawn that moved two squares can be taken en passant on the square it skipped, but only
+        // on the very next move
+        self.en_passant = None;
+        if the_piece.piece == PieceType::Pawn && mv.from.rank().abs_diff(mv.to.rank()) == 2 {
+            self.en_passant = Square::new(mv.from.file(), (mv.from.rank() + mv.to.rank()) / 2);
+        }
+
+        // promotions and en passant count as pawn moves, resetting the clock as well
+        if the_piece.piece == PieceType::Pawn || record.captured.is_some() {
+            self.halfmove_clock = 0;
+        }
+        else {
+            self.halfmove_clock += 1;
+        }
+        if self.turn == Color::Black {
+            self.fullmove_number += 1;
+        }
+        // changing turn
+        self.turn = self.turn.opposite();
+        record
+    }
+
+    // takes back a move played with make_move
+    pub(crate) fn unmake_move(&mut self, record: &MoveRecord) {
+        let mv = record.mv;
+        self.set_piece(mv.to, None);
+        self.set_piece(mv.from, Some(record.piece));
+        if Game::is_en_passant(&mv, &Some(record.piece), &None) && record.en_passant == Some(mv.to) {
+            self.set_piece(en_passant_capture(&mv), record.captured);
+        }
+        else {
+            self.set_piece(mv.to, record.captured);
+        }
+        if Game::is_castling(&mv, &Some(record.piece)) {
+            let (rook_start, rook_end) = castling_rook_squares(&mv);
+            self.set_piece(rook_start, self.piece_at(rook_end));
+            self.set_piece(rook_end, None);
+        }
+        self.turn = record.piece.color;
+        self.castling = record.castling;
+        self.en_passant = record.en_passant;
+        self.halfmove_clock = record.halfmove_clock;
+        self.fullmove_number = record.fullmove_number;
+    }
+
+    fn play_move(&mut self, mv: Move) -> result::Result<MoveOutcome, MoveError> {
         if self.finished {
             return Err(MoveError::Mated);
         }
-        // dbg!(mv);
-        let the_piece = match self.board[mv.start_y][mv.start_x] {
-            None => return Err(MoveError::NoPiece),
+        if let Some(x) = self.move_error(&mv) {
+            return Err(x);
+        }
+        let position = self.repetition_key();
+        let record = self.make_move(mv);
+        debug_assert_eq!(self.hash_key(), self.compute_hash_key());
+        self.move_history.push(record);
+        self.positions.push(position);
+
+        self.termination = self.automatic_termination();
+        self.finished = self.termination.is_some();
+        let outcome = MoveOutcome {
+            captured: record.captured,
+            check: self.in_check(),
+            termination: self.termination,
+            decision: self.decision(),
+        };
+        self.report_move(&mv, &outcome);
+        Ok(outcome)
+    }
+
+    // the ways a game ends right after a move, without anyone having to claim anything
+    fn automatic_termination(&self) -> Option<Termination> {
+        // continue to mate check, the side to move now needs at least one safe move
+        if self.legal_moves().is_empty() {
+            // without a safe move and without being in check, the game is a stalemate
+            if !self.in_check() {
+                return Some(Termination::Stalemate);
+            }
+            // the side that just moved has won
+            return Some(Termination::Checkmate);
+        }
+        // when neither side can checkmate anymore the game is over
+        if self.is_insufficient_material(Color::White) && self.is_insufficient_material(Color::Black) {
+            return Some(Termination::InsufficientMaterial);
+        }
+        // after 75 moves by each side without a capture or pawn move the game is drawn without
+        // anyone having to claim it, like FIDE does
+        if self.halfmove_clock >= 150 {
+            return Some(Termination::SeventyFiveMoveRule);
+        }
+        // and the fifth time the same position comes up, it is drawn as well
+        if self.repetitions() >= 5 {
+            return Some(Termination::FivefoldRepetition);
+        }
+        None
+    }
+
+    // every fully legal move for the side to move
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::new();
+        self.generate_moves(self.colors[self.turn as usize], &mut moves);
+        moves
+    }
+
+    // every fully legal move for the piece standing on the square, empty if it isn't the side to
+    // move's piece
+    pub fn legal_moves_from(&self, square: Square) -> Vec<Move> {
+        let mut moves = Vec::new();
+        self.generate_moves(bit(square), &mut moves);
+        moves
+    }
+
+    // adds the legal moves of the side to move's pieces standing on `from` to `moves`
+    pub(crate) fn generate_moves(&self, from: Bitboard, moves: &mut Vec<Move>) {
+        if self.finished {
+            return;
+        }
+        let own = self.colors[self.turn as usize];
+        let enemy = self.colors[self.turn.opposite() as usize];
+        let occupied = own | enemy;
+        for square in squares(from & own) {
+            let the_piece = self.squares[square.index()].unwrap();
+            let targets = match the_piece.piece {
+                PieceType::Pawn => {
+                    let mut targets = pawn_attacks(self.turn, square) & (enemy | self.en_passant.map_or(0, bit));
+                    // one square forward, and a second one from the starting rank, onto empty squares
+                    let (ahead, start_rank) = match self.turn {
+                        Color::White => (square.rank() + 1, 1),
+                        Color::Black => (square.rank().wrapping_sub(1), 6),
+                    };
+                    if let Some(one) = Square::new(square.file(), ahead).filter(|&s| occupied & bit(s) == 0) {
+                        targets |= bit(one);
+                        let two = if self.turn == Color::White { ahead + 1 } else { ahead.wrapping_sub(1) };
+                        match Square::new(square.file(), two) {
+                            Some(two) if square.rank() == start_rank && occupied & bit(two) == 0 => targets |= bit(two),
+                            _ => {},
+                        }
+                    }
+                    targets
+                }
+                PieceType::Knight => knight_attacks(square),
+                PieceType::Bishop => bishop_attacks(square, occupied),
+                PieceType::Rook => rook_attacks(square, occupied),
+                PieceType::Queen => bishop_attacks(square, occupied) | rook_attacks(square, occupied),
+                PieceType::King => king_attacks(square),
+            } & !own;
+
+            for to in squares(targets) {
+                let mv = Move::new(square, to);
+                if !self.king_safe_after(&mv, the_piece) {
+                    continue;
+                }
+                // a pawn reaching the last rank gives one move per piece it can turn into
+                if the_piece.piece == PieceType::Pawn && bit(to) & (RANK_1 | RANK_8) != 0 {
+                    for piece in [PieceType::Queen, PieceType::Rook, PieceType::Bishop, PieceType::Knight] {
+                        moves.push(Move::promote(square, to, piece));
+                    }
+                }
+                else {
+                    moves.push(mv);
+                }
+            }
+            if the_piece.piece == PieceType::King {
+                let castles = [square.file().checked_add(2), square.file().checked_sub(2)];
+                for to in castles.iter().flatten().filter_map(|&file| Square::new(file, square.rank())) {
+                    let mv = Move::new(square, to);
+                    if self.castling_error(&mv, self.turn).is_none() {
+                        moves.push(mv);
+                    }
+                }
+            }
+        }
+    }
+
+    // everything that can make a move illegal for the side to move, None if the move can be played
+    fn move_error(&self, mv: &Move) -> Option<MoveError> {
+        let the_piece = match self.piece_at(mv.from) {
+            None => return Some(MoveError::NoPiece),
             Some(p) => {
                 if p.color != self.turn {
-                    return Err(MoveError::WrongColorPiece)
+                    return Some(MoveError::WrongColorPiece)
                 }
                 else {
                     Some(p)
@@ -152,519 +643,276 @@ impl Game {
         
         let mut capture: bool = false;
 
-        let end_square = match self.board[mv.end_y][mv.end_x] {
+        let end_square = match self.piece_at(mv.to) {
             None => Option::<Piece>::None, 
             Some(p) => if p.color == self.turn {
-                return Err(MoveError::FriendlyFire)
+                return Some(MoveError::FriendlyFire)
             }
             else {
                 capture = true;
                 Some(p)
             }, 
         };
-        // dbg!(the_piece);
-        // dbg!(end_square);
 
-        match self.legal_movement(&mv, &the_piece, &end_square, capture) { 
-            Some(x) => {
-                return Err(x);
-            }
-            None => {
-                // just a normal move
-            }
+        if let Some(x) = self.legal_movement(mv, &the_piece, &end_square, capture) {
+            return Some(x);
         }
-        // dbg!(self.legal_movement(&mv, &the_piece, &end_square, capture));
 
-        let saved_start: Option<Piece> = self.board[mv.start_y][mv.start_x].clone();
-        let saved_end: Option<Piece> = self.board[mv.end_y][mv.end_x].clone();
+        // make sure the king isn't left in check
+        if !self.king_safe_after(mv, the_piece.unwrap()) {
+            return Some(MoveError::SelfCheck);
+        }
+        None
+    }
 
+    // whether the moving side's king is safe after the move, worked out from the bitboards
+    // without playing it
+    fn king_safe_after(&self, mv: &Move, the_piece: Piece) -> bool {
+        let color = the_piece.color;
+        let mut occupied = (self.occupied() & !bit(mv.from)) | bit(mv.to);
+        let mut enemy = self.colors[color.opposite() as usize] & !bit(mv.to);
+        if Game::is_en_passant(mv, &Some(the_piece), &self.piece_at(mv.to)) {
+            let captured = bit(en_passant_capture(mv));
+            occupied &= !captured;
+            enemy &= !captured;
+        }
+        let king = if the_piece.piece == PieceType::King { Some(mv.to) } else { self.king_square(color) };
+        match king {
+            Some(king) => self.attackers(king, color.opposite(), occupied) & enemy == 0,
+            None => true,
+        }
+    }
 
-        // dbg!(self.board[mv.start_y][mv.start_x]);
-        // dbg!(self.board[mv.end_y][mv.end_x]);
-        // potentially temporarily make the move
-        self.board[mv.start_y][mv.start_x] = None;
-        self.board[mv.end_y][mv.end_x] = the_piece;
-        
-        if self.in_check() { 
-            // dbg!("thinks in check");
-            self.board[mv.start_y][mv.start_x] = saved_start;
-            self.board[mv.end_y][mv.end_x] = saved_end;
-            return Err(MoveError::SelfCheck);
-            // return some in_check error
-        }
-        // dbg!("after check check");
-        // dbg!(self.board[mv.start_y][mv.start_x]);
-        // dbg!(self.board[mv.end_y][mv.end_x]);
-
-        // else continue to mate check
-        let mut safe_move: bool = false;
-
-        // i'm sure there is a better way or writing this mate check. The complexity is through the
-        // roof here.
-        // temporarily change color 
-        match self.turn {
-            Color::White => self.turn = Color::Black,
-            Color::Black => self.turn = Color::White,
-        }
-        // dbg!(self.turn);
-        'move_gen: for org_y in 0..8 {
-            for org_x in 0..8 {
-                for dest_y in 0..8 {
-                    for dest_x in 0..8 {
-                        let cur_move: Move = Move{start_x: org_x, start_y: org_y, end_x: dest_x, end_y: dest_y};
-                        let cur_piece = match self.board[org_y][org_x] {
-                            None => continue,
-                            Some(p) => if p.color != self.turn {
-                                continue
-                            }
-                            else {
-                                Some(p)
-                            },
-                        };
-                        
-                        let mut cur_capture: bool = false;
-
-                        let cur_end_square = match self.board[dest_y][dest_x] {
-                            None => Option::<Piece>::None, 
-                            Some(p) => if p.color == self.turn {
-                                continue 
-                            }
-                            else {
-                                cur_capture = true;
-                                Some(p)
-                            }, 
-                        };
-                        if cur_move == (Move {start_x: 5, start_y: 1, end_x: 5, end_y: 2}) {
-                            // dbg!(cur_piece);
-                            // dbg!(cur_end_square);
-                        }
+    // the pieces of color `by` attacking the square, with `occupied` blocking the sliding pieces
+    fn attackers(&self, square: Square, by: Color, occupied: Bitboard) -> Bitboard {
+        let diagonal = self.pieces[PieceType::Bishop as usize] | self.pieces[PieceType::Queen as usize];
+        let straight = self.pieces[PieceType::Rook as usize] | self.pieces[PieceType::Queen as usize];
+        // a pawn attacks the square if a pawn of the other color on the square would attack it
+        ((knight_attacks(square) & self.pieces[PieceType::Knight as usize])
+            | (king_attacks(square) & self.pieces[PieceType::King as usize])
+            | (pawn_attacks(by.opposite(), square) & self.pieces[PieceType::Pawn as usize])
+            | (bishop_attacks(square, occupied) & diagonal)
+            | (rook_attacks(square, occupied) & straight))
+            & self.colors[by as usize]
+    }
 
-                        if self.legal_movement(&cur_move, &cur_piece, &cur_end_square, cur_capture).is_none() {
-                            let saved_start: Option<Piece> = self.board[cur_move.start_y][cur_move.start_x].clone();
-                            let saved_end: Option<Piece> = self.board[cur_move.end_y][cur_move.end_x].clone();
-                            if cur_move == (Move {start_x: 5, start_y: 1, end_x: 5, end_y: 2}) {
-                                // dbg!("IN");
-                                // dbg!(saved_start);
-                                // dbg!(saved_end);
-                            }
-                            self.board[cur_move.start_y][cur_move.start_x] = None; 
-                            self.board[cur_move.end_y][cur_move.end_x] = saved_start.clone(); 
-                            if !self.in_check() {
-                                safe_move = true; 
-                                self.board[cur_move.start_y][cur_move.start_x] = saved_start; 
-                                self.board[cur_move.end_y][cur_move.end_x] = saved_end; 
-                                break 'move_gen;
-                            }
-                            self.board[cur_move.start_y][cur_move.start_x] = saved_start; 
-                            self.board[cur_move.end_y][cur_move.end_x] = saved_end; 
-                        }
-                    }
-                }
-            }
+    // a pawn moving diagonally onto an empty square can only be capturing en passant
+    fn is_en_passant(mv: &Move, the_piece: &Option<Piece>, end_square: &Option<Piece>) -> bool {
+        match the_piece {
+            Some(p) => p.piece == PieceType::Pawn && mv.from.file() != mv.to.file() && end_square.is_none(),
+            None => false,
         }
-        // switch color back
-        match self.turn {
-            Color::White => self.turn = Color::Black,
-            Color::Black => self.turn = Color::White,
-        }
-        // dbg!(safe_move);
-        if !safe_move {
-            // self.turn has won
-            // signal end of game or something
-            // dbg!("got here");
-            let winner = match self.turn {
-                Color::White => "White",
-                Color::Black => "Black",
-            };
-            let loser = match self.turn {
-                Color::White => "Black",
-                Color::Black => "White",
-            };
-            let decision = match self.turn { 
-                Color::White => Decision::White,
-                Color::Black => Decision::Black,
-            };
-            println!("{} has checkmated {}, and won the game", winner, loser);
-            return Ok(Some(decision));
+    }
+
+    // a king moving two squares sideways is an attempt to castle
+    fn is_castling(mv: &Move, the_piece: &Option<Piece>) -> bool {
+        match the_piece {
+            Some(p) => p.piece == PieceType::King && mv.from.rank() == mv.to.rank() && mv.from.file().abs_diff(mv.to.file()) == 2,
+            None => false,
         }
-        else {
-            if saved_start.unwrap().piece == PieceType::Pawn {
-                self.move_history.push(MoveType::CaptureOrPawn);
-            }
-            else {
-                match saved_end {
-                    None => {
-                        self.move_history.push(MoveType::CaptureOrPawn);
-                    }, 
-                    _ => {
-                        self.move_history.push(MoveType::Other);
-                    }
+    }
+
+    // moves the piece, along with the rook when castling and the captured pawn when taking en
+    // passant. legality is assumed to have been checked already
+    fn move_pieces(&mut self, mv: &Move, the_piece: Piece) {
+        if Game::is_en_passant(mv, &Some(the_piece), &self.piece_at(mv.to)) {
+            self.set_piece(en_passant_capture(mv), None);
+        }
+        self.set_piece(mv.from, None);
+        self.set_piece(mv.to, match mv.promotion {
+            Some(piece) => Some(Piece{piece, color: the_piece.color}),
+            None => Some(the_piece),
+        });
+        if Game::is_castling(mv, &Some(the_piece)) {
+            // the king has already been moved, the rook jumps over to the other side of it
+            let (rook_start, rook_end) = castling_rook_squares(mv);
+            self.set_piece(rook_end, self.piece_at(rook_start));
+            self.set_piece(rook_start, None);
+        }
+    }
+
+    // a king move loses both rights, and anything moving from or onto a rook's corner loses that
+    // side's right (the rook either moved or got captured)
+    fn update_castling_rights(&mut self, mv: &Move, the_piece: Piece) {
+        if the_piece.piece == PieceType::King {
+            match the_piece.color {
+                Color::White => {
+                    self.castling.white_king_side = false;
+                    self.castling.white_queen_side = false;
+                }
+                Color::Black => {
+                    self.castling.black_king_side = false;
+                    self.castling.black_queen_side = false;
                 }
             }
         }
-        // check for 50 move draw rule, and force draw like in chess com
-        let mut pawn_capture_move: bool = false;
-        let LEN: isize = self.move_history.len() as isize;
-        for i in (LEN - min(50, LEN)..LEN).rev() {
-            if self.move_history[i as usize] == MoveType::CaptureOrPawn {
-                pawn_capture_move = true;
-                break;
+        for square in [mv.from, mv.to] {
+            match square {
+                Square::H1 => self.castling.white_king_side = false,
+                Square::A1 => self.castling.white_queen_side = false,
+                Square::H8 => self.castling.black_king_side = false,
+                Square::A8 => self.castling.black_queen_side = false,
+                _ => {}
             }
         }
-        if !pawn_capture_move {
-            // the game is drawn
-            // end game or something
-            // dont know println is the best way to handle this
-            println!("The game is drawn because 50 reversible moves have been played");
-            self.finished = true;
-            return Ok(Some(Decision::Tie));
+    }
+
+    // checks whether castling is allowed, the king is assumed to be moving two squares sideways
+    fn castling_error(&self, mv: &Move, color: Color) -> Option<MoveError> {
+        let home_rank = match color {
+            Color::White => 0,
+            Color::Black => 7,
+        };
+        if mv.from.file() != 4 || mv.from.rank() != home_rank {
+            return Some(MoveError::Movement);
         }
-        // changing turn
-        match self.turn {
-            Color::White => {self.turn = Color::Black;}, 
-            Color::Black => {self.turn = Color::White;}, 
+        let (allowed, rook_file) = match (color, mv.to.file() > mv.from.file()) {
+            (Color::White, true) => (self.castling.white_king_side, 7),
+            (Color::White, false) => (self.castling.white_queen_side, 0),
+            (Color::Black, true) => (self.castling.black_king_side, 7),
+            (Color::Black, false) => (self.castling.black_queen_side, 0),
+        };
+        let rook = Square::new(rook_file, home_rank).unwrap();
+        if !allowed || self.piece_at(rook) != Some(Piece{piece: PieceType::Rook, color}) {
+            return Some(MoveError::Movement);
+        }
+        if between(mv.from, rook) & self.occupied() != 0 {
+            return Some(MoveError::BlockedPath);
+        }
+        // the king may not castle out of, through or into check
+        for square in squares(between(mv.from, mv.to) | bit(mv.from) | bit(mv.to)) {
+            if self.square_attacked(square, color.opposite()) {
+                return Some(MoveError::CastleThroughCheck);
+            }
         }
-        return Ok(None);
+        None
     }
 
     // checks for move legality
-    pub fn legal_movement(&mut self, mv: &Move, the_piece: &Option<Piece>, end_square: &Option<Piece>, capture: bool) -> Option<MoveError> {
+    pub fn legal_movement(&self, mv: &Move, the_piece: &Option<Piece>, _end_square: &Option<Piece>, capture: bool) -> Option<MoveError> {
         /* check possible mv errors in order */
-        let board_y = 0..8;
-        let board_x = 0..8;
         // if the position doesnt change
-        if mv.start_x == mv.end_x && mv.start_y == mv.end_y { 
-            // dbg!("thinks its same");
+        if mv.from == mv.to { 
             return Some(MoveError::Movement);
         }
-        // if the moves is in bounds
-        if !board_x.contains(&mv.start_x) || !board_x.contains(&mv.end_x) || !board_y.contains(&mv.start_y) || !board_y.contains(&mv.end_y) {
-            // dbg!("thinks its out of bounds");
-            return Some(MoveError::OutsideBoard);
-        }
-        let dx: isize = { 
-            if (mv.end_x as isize) - (mv.start_x as isize) > 0 {1}
-            else if (mv.end_x as isize) - (mv.start_x as isize) < 0 {-1}
-            else {0}
-        };
-        let dy: isize = { 
-            if (mv.end_y as isize) - (mv.start_y as isize) > 0 {1}
-            else if (mv.end_y as isize) - (mv.start_y as isize) < 0 {-1}
-            else {0}
-        };
+        let file_dif = (mv.to.file() as isize - mv.from.file() as isize).abs();
+        let rank_dif = mv.to.rank() as isize - mv.from.rank() as isize;
 
         match the_piece.unwrap().piece {
             /* check if move is even legal */
             // check if it right type of move 
             PieceType::Pawn => {
-                // dbg!("thinks its pawn");
-                if (mv.end_x as isize - mv.start_x as isize).abs() != { if capture {1} else {0} } {
-                    // dbg!("thinks pawn is not moving to the side");
-                    return Some(MoveError::Movement);
-                }
-                let y_dif = mv.end_y as isize - mv.start_y as isize;
-                // dbg!(y_dif);
-                // todo! : make sure the pawn doesnt move back
-                if mv.start_y == 1 || mv.start_y == 6 {
-                    if ((y_dif > 2 || y_dif < 1) && self.turn == Color::White) || ((y_dif < -2 || y_dif > -1) && self.turn == Color::Black) {
-                        // dbg!("thinks its moving more than 2 steps in y");
+                let (forward, start_rank): (isize, usize) = match the_piece.unwrap().color {
+                    Color::White => (1, 1),
+                    Color::Black => (-1, 6),
+                };
+                if file_dif == 1 {
+                    // captures go one square diagonally forward, onto an enemy piece or the en passant square
+                    if rank_dif != forward || !(capture || self.en_passant == Some(mv.to)) {
                         return Some(MoveError::Movement);
                     }
                 }
-                else {
-                    // todo make the rest of the movement statements like this vvv
-                    if (y_dif != 1 && self.turn == Color::White) || (y_dif != -1 && self.turn == Color::Black) {
-                        // dbg!("thinks its moving more than 1 steps in y");
-                        return Some(MoveError::Movement);
+                else if file_dif != 0 || capture {
+                    return Some(MoveError::Movement);
+                }
+                else if rank_dif == 2 * forward && mv.from.rank() == start_rank {
+                    // the square in between has to be free as well
+                    if let Some(e) = self.path_error(mv) {
+                        return Some(e);
                     }
                 }
+                else if rank_dif != forward {
+                    return Some(MoveError::Movement);
+                }
             }
             PieceType::Knight => {
-                let y_dif: isize = (mv.end_y as isize - mv.start_y as isize).abs(); 
-                let x_dif: isize = (mv.end_x as isize - mv.start_x as isize).abs(); 
-                if max(y_dif, x_dif) != 2 || min(y_dif, x_dif) != 1 {
+                let rank_dif = rank_dif.abs();
+                if max(rank_dif, file_dif) != 2 || min(rank_dif, file_dif) != 1 {
                     return Some(MoveError::Movement);
                 }
                 // should always have legal Movement at this stage
             }
             PieceType::Bishop => {
-                if (mv.end_y as isize - mv.start_y as isize).abs() != (mv.end_x as isize - mv.start_x as isize).abs() {
+                if rank_dif.abs() != file_dif {
                     return Some(MoveError::Movement);
                 }
 
-                let mut cur_x: isize =  mv.start_x as isize;
-                let mut cur_y: isize = mv.start_y as isize;
-                while (cur_x != mv.end_x as isize) || (cur_y != mv.end_y as isize) {
-                    if  (cur_x != mv.start_x as isize) || (cur_y != mv.start_y as isize) {
-                        if self.board[cur_y as usize][cur_x as usize].is_some() {
-                            return Some(MoveError::BlockedPath);
-                        }
-                    }
-                    cur_x += dx as isize;
-                    cur_y += dy as isize;
+                if let Some(e) = self.path_error(mv) {
+                    return Some(e);
                 }
-
             }
             PieceType::Rook => {
-                if mv.end_y as isize - mv.start_y as isize != 0 && mv.end_x as isize - mv.end_x as isize != 0 { 
+                if rank_dif != 0 && file_dif != 0 { 
                     return Some(MoveError::Movement);
                 }
 
-                let mut cur_x: isize = mv.start_x as isize;
-                let mut cur_y: isize = mv.start_y as isize;
-                while (cur_x != mv.end_x as isize) || (cur_y != mv.end_y as isize) {
-                    if  (cur_x != mv.start_x as isize) || (cur_y != mv.start_y as isize) {
-                        if self.board[cur_y as usize][cur_x as usize].is_some() {
-                            return Some(MoveError::BlockedPath);
-                        }
-                    }
-                    cur_x += dx as isize;
-                    cur_y += dy as isize;
+                if let Some(e) = self.path_error(mv) {
+                    return Some(e);
                 }
             }
             PieceType::Queen => {
-                let Y = (mv.end_y as isize - mv.start_y as isize).abs();
-                let X = (mv.end_x as isize - mv.start_x as isize).abs();
-                if max(X, Y) != min(X, Y) && min(X, Y) != 0 {
+                let rank_dif = rank_dif.abs();
+                if max(file_dif, rank_dif) != min(file_dif, rank_dif) && min(file_dif, rank_dif) != 0 {
                     return Some(MoveError::Movement);
                 }
-                let mut cur_x = mv.start_x as isize;
-                let mut cur_y = mv.start_y as isize;
-                while (cur_x != mv.end_x as isize) || (cur_y != mv.end_y as isize) {
-                    if  (cur_x != mv.start_x as isize) || (cur_y != mv.start_y as isize) {
-                        if self.board[cur_y as usize][cur_x as usize].is_some() {
-                            return Some(MoveError::BlockedPath);
-                        }
-                    }
-                    cur_x += dx as isize;
-                    cur_y += dy as isize;
+                if let Some(e) = self.path_error(mv) {
+                    return Some(e);
                 }
             }
             PieceType::King => {
-                let Y = (mv.end_y as isize - mv.start_y as isize).abs();
-                let X = (mv.end_x as isize - mv.start_x as isize).abs();
-                if max(Y, X) != 1 {
+                let rank_dif = rank_dif.abs();
+                // the attempted move is two files sideways, an attempt to castle
+                if rank_dif == 0 && file_dif == 2 {
+                    return self.castling_error(mv, the_piece.unwrap().color);
+                }
+                if max(rank_dif, file_dif) != 1 {
                     return Some(MoveError::Movement);
                 }
-                // check if the attempted move is dx = 2 (potential attempt to castle)
-                // otherwise there should be no problem
             }
         }
-        
-        return None;
-    }
 
-    pub fn in_check(&mut self) -> bool {
-        /* FOR checking if a move is legal */ 
-        // make a copy of the board 
-        // find king
-        let mut king_x: isize = 0;
-        let mut king_y: isize = 0;
-        'outer: for i in 0..8 {
-            for j in 0..8 {
-                match self.board[i as usize][j as usize] {
-                    None => continue,
-                    Some(p) => if p.color == self.turn && p.piece == PieceType::King {
-                        king_y = i;
-                        king_x = j;
-                        break 'outer;
-                    },
-                }
-            }
-        }
-        // cast a ray from the king in 8 directions 
-        let dir: [[isize; 2]; 8] = [[-1, -1], [-1, 0], [0, -1], [0,1], [1, 0], [-1, 1], [1, -1], [1, 1]];
-        let mut checked: bool = false;
-        'outer: for i in 0..8 {
-            let mut cur_x = king_x;
-            let mut cur_y = king_y;
-            let DX = dir[i as usize][0 as usize];
-            let DY = dir[i as usize][1 as usize];
-            while 0 <= cur_x+DX && cur_x+DX < 8 && 0 <= cur_y+DY && cur_y+DY < 8 {
-                cur_x += DX;
-                cur_y += DY;
-                match self.board[cur_y as usize][cur_x as usize] {
-                    Some(p) => if p.color != self.turn {
-                        match p.piece {
-                            PieceType::Rook => if min(DX.abs(), DY.abs()) == 0 {
-                                // dbg!(p.piece);
-                                checked = true;
-                                break;
-                            },
-                            PieceType::Bishop => if min(DX.abs(), DY.abs()) != 0 {
-                                // dbg!(p.piece);
-                                checked = true;
-                                break;
-                            },
-                            PieceType::Queen => {
-                                // dbg!(p.piece);
-                                checked = true;
-                                break;
-                            },
-                            PieceType::Pawn => {
-                                match self.turn {
-                                    Color::White => if (cur_x - king_x).abs() == 1 && cur_y - king_y == 1 {
-                                        // dbg!(p.piece);
-                                        checked = true;
-                                        break;
-                                    }
-                                    Color::Black => if (cur_x - king_x).abs() == 1 && cur_y - king_y == -1 {
-                                        // dbg!(p.piece);
-                                        checked = true;
-                                        break;
-                                    }
-                                }
-                            }
-                            PieceType::King => if max((cur_x-king_x).abs(), (cur_y-king_y).abs()) == 1 {
-                                checked = true;
-                            },
-                            _ => {},
-                        }
-                    }
-                    else {
-                        break;
-                    },
-                    None => {},
-                }
-            }
+        // only a pawn reaching the last rank may, and must, be promoted
+        let promoting = the_piece.unwrap().piece == PieceType::Pawn && bit(mv.to) & (RANK_1 | RANK_8) != 0;
+        match mv.promotion {
+            None if promoting => Some(MoveError::MissingPromotion),
+            Some(PieceType::Knight | PieceType::Bishop | PieceType::Rook | PieceType::Queen) if promoting => None,
+            Some(_) => Some(MoveError::InvalidPromotion),
+            None => None,
         }
-        // check 1 knight move away
-        let knight_dir: [[isize; 2]; 8] = [[2, -1], [2, 1], [-2, -1], [-2, 1], [1, 2], [1, -2], [-1, 2], [-1, -2]];
-        'outer: for i in 0..8 {
-            let cur_x = king_x + knight_dir[i as usize][0];
-            let cur_y = king_y + knight_dir[i as usize][1];
-            if 0 > cur_x || cur_x > 7 || 0 > cur_y || cur_y > 7 {
-                continue;
-            }
-            if self.board[cur_y as usize][cur_x as usize] != None && self.board[cur_y as usize][cur_x as usize].unwrap().color != self.turn {
-                checked = true;
-                break 'outer;
-            }
-        }
-        return checked;
     }
 
-    pub fn game_from_fen(s: &str) -> Game {
-        let mut g = Game::empty_game();
-        let mut row: isize = 7;
-        let mut col: isize = 7; 
-        let mut space_found = false;
-        for x in s.chars() {
-            if space_found {
-                match x {
-                    'w' => {
-                        g.turn = Color::White;
-                    }
-                    'b' => {
-                        g.turn = Color::Black;
-                    }
-                    _ => {
-
-                    }
-                }
-                break;
-            }
-            match x {
-                ' ' => {
-                    // dbg!(1);
-                    space_found = true;
-                }
-                '/' => {
-                    // dbg!(2);
-                    row -= 1;
-                    col = 7;
-                    continue;
-                }
-                'P' => {
-                    // dbg!(3);
-                    g.board[row as usize][col as usize] = Some(Piece{piece: PieceType::Pawn, color: Color::White});
-                }
-                'p' => {
-                    // dbg!(4);
-                    g.board[row as usize][col as usize] = Some(Piece{piece: PieceType::Pawn, color: Color::Black});
-                }
-                'N' => {
-                    // dbg!(5);
-                    g.board[row as usize][col as usize] = Some(Piece{piece: PieceType::Knight, color: Color::White});
-                }
-                'n' => {
-                    // dbg!(6);
-                    g.board[row as usize][col as usize] = Some(Piece{piece: PieceType::Knight, color: Color::Black});
-                }
-                'B' => {
-                    // dbg!(7);
-                    g.board[row as usize][col as usize] = Some(Piece{piece: PieceType::Bishop, color: Color::White});
-                }
-                'b' => {
-                    // dbg!(8);
-                    g.board[row as usize][col as usize] = Some(Piece{piece: PieceType::Bishop, color: Color::Black});
-                }
-                'R' => {
-                    // dbg!(9);
-                    g.board[row as usize][col as usize] = Some(Piece{piece: PieceType::Rook, color: Color::White});
-                }
-                'r' => {
-                    // dbg!(1);
-                    g.board[row as usize][col as usize] = Some(Piece{piece: PieceType::Rook, color: Color::Black});
-                }
-                'Q' => {
-                    // dbg!(12);
-                    g.board[row as usize][col as usize] = Some(Piece{piece: PieceType::Queen, color: Color::White});
-                }
-                'q' => {
-                    // dbg!(13);
-                    g.board[row as usize][col as usize] = Some(Piece{piece: PieceType::Queen, color: Color::Black});
-                }
-                'K' => {
-                    // dbg!(14);
-                    g.board[row as usize][col as usize] = Some(Piece{piece: PieceType::King, color: Color::White});
-                }
-                'k' => {
-                    // dbg!(15);
-                    g.board[row as usize][col as usize] = Some(Piece{piece: PieceType::King, color: Color::Black});
-                }
-                '1' => {
-                    col -= 1;
-                    continue;
-                }
-                '2' => {
-                    col -= 2;
-                    continue;
-                }
-                '3' => {
-                    col -= 3;
-                    continue;
-                }
-                '4' => {
-                    col -= 4;
-                    continue;
-                }
-                '5' => {
-                    col -= 5;
-                    continue;
-                }
-                '6' => {
-                    col -= 6;
-                    continue;
-                }
-                '7' => {
-                    col -= 7;
-                    continue;
-                }
-                '8' => {
-                    col -= 8;
-                    continue;
+    // complains about anything standing between the start and end square
+    fn path_error(&self, mv: &Move) -> Option<MoveError> {
+        if between(mv.from, mv.to) & self.occupied() != 0 {
+            return Some(MoveError::BlockedPath);
+        }
+        None
+    }
 
-                }
-                _ => {}
-            }
-            col -= 1;
+    pub fn in_check(&self) -> bool {
+        /* FOR checking if a move is legal */ 
+        match self.king_square(self.turn) {
+            Some(king) => self.square_attacked(king, self.turn.opposite()),
+            None => false,
         }
-        // i guess move history can be ignored for this
-        return g;
+    }
+
+    // checks if any piece of color `by` attacks the square
+    pub fn square_attacked(&self, square: Square, by: Color) -> bool {
+        self.attackers(square, by, self.occupied()) != 0
     }
 }
 
+// the pawn taken en passant stands beside the start square, not on the end square
+fn en_passant_capture(mv: &Move) -> Square {
+    Square::new(mv.to.file(), mv.from.rank()).unwrap()
+}
+
+// where the rook starts and ends up when castling
+fn castling_rook_squares(mv: &Move) -> (Square, Square) {
+    let (start, end) = if mv.to.file() > mv.from.file() { (7, 5) } else { (0, 3) };
+    (Square::new(start, mv.from.rank()).unwrap(), Square::new(end, mv.from.rank()).unwrap())
+}
 
 #[cfg(test)]
 mod tests {
@@ -677,100 +925,399 @@ mod tests {
         assert_eq!(base_new_game, fen_game);
     }
 
-    // #[test]
+    #[test]
+    pub fn board_indexing() {
+        let game = Game::game_from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+        let board = game.board();
+        for square in Square::all() {
+            assert_eq!(game[square], game.piece_at(square));
+            assert_eq!(game[(square.x(), square.y())], board[square.y()][square.x()]);
+        }
+        // x = 3 is the e-file in the old layout
+        assert_eq!(game[(3, 3)], Some(Piece { piece: PieceType::Pawn, color: Color::White }));
+        assert_eq!(game[(7, 0)], Some(Piece { piece: PieceType::Rook, color: Color::White }));
+    }
+
+    #[test]
     pub fn check_new_game_wrong() {
         let base_new_game = Game::new_game(); 
+        // a white rook where black's rook belongs
         let fen_game = Game::game_from_fen("Rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
-        assert_eq!(base_new_game, fen_game);
+        assert_ne!(base_new_game, fen_game);
+        assert_ne!(base_new_game.board(), fen_game.board());
     }
 
     #[test]
     pub fn pawn_move() {
         let mut pawn_move = Game::new_game();
-        pawn_move.do_move(Move{start_x: 3, start_y: 1, end_x: 3, end_y: 3});
+        pawn_move.do_move(Move::new(Square::E2, Square::E4)).unwrap();
         let fen_game = Game::game_from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
-        assert_eq!(pawn_move.board, fen_game.board);
+        assert_eq!(pawn_move.board(), fen_game.board());
     }
     
-    // #[test]
+    #[test]
     pub fn doesnt_move() {
         let mut pawn_move = Game::new_game();
-        pawn_move.do_move(Move{start_x: 3, start_y: 1, end_x: 3, end_y: 3});
-        let base_game = Game::game_from_fen("Rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
-        assert_eq!(pawn_move.board, base_game.board);
+        pawn_move.do_move(Move::new(Square::E2, Square::E4)).unwrap();
+        // the pawn has left its square
+        assert_eq!(pawn_move.piece_at(Square::E2), None);
+        assert_ne!(pawn_move.board(), Game::new_game().board());
     }
 
     #[test]
     pub fn knight_move() {
         let mut knight_move = Game::new_game();
-        knight_move.do_move(Move { start_x: 1, start_y: 0, end_x: 2, end_y: 2});
+        knight_move.do_move(Move::new(Square::G1, Square::F3)).unwrap();
         let fen_game = Game::game_from_fen("rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKB1R b KQkq - 1 1");
-        assert_eq!(knight_move.board, fen_game.board);
+        assert_eq!(knight_move.board(), fen_game.board());
     }
 
     #[test]
     pub fn italian_game() {
         let mut italian_game = Game::new_game();
-        italian_game.do_move(Move{start_x: 3, start_y: 1, end_x: 3, end_y: 3});
-        italian_game.do_move(Move { start_x: 3, start_y: 6, end_x: 3, end_y: 4});
-        italian_game.do_move(Move{start_x: 1, start_y: 0, end_x: 2, end_y: 2});
-        italian_game.do_move(Move{start_x: 6, start_y: 7, end_x: 5, end_y: 5});
-        italian_game.do_move(Move{start_x: 2, start_y: 0, end_x: 5, end_y: 3});
+        italian_game.do_move(Move::new(Square::E2, Square::E4)).unwrap();
+        italian_game.do_move(Move::new(Square::E7, Square::E5)).unwrap();
+        italian_game.do_move(Move::new(Square::G1, Square::F3)).unwrap();
+        italian_game.do_move(Move::new(Square::B8, Square::C6)).unwrap();
+        italian_game.do_move(Move::new(Square::F1, Square::C4)).unwrap();
         // let fen_game = Game::game_from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3");
         let fen_game = Game::game_from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3");
-        assert_eq!(italian_game.board, fen_game.board);
+        assert_eq!(italian_game.board(), fen_game.board());
     }
 
     #[test]
     pub fn pin_ruy_lopez() {
         let mut pin_ruy_lopez = Game::new_game();
-        pin_ruy_lopez.do_move(Move{start_x: 3, start_y: 1, end_x: 3, end_y: 3});
-        pin_ruy_lopez.do_move(Move { start_x: 3, start_y: 6, end_x: 3, end_y: 4});
-        pin_ruy_lopez.do_move(Move{start_x: 1, start_y: 0, end_x: 2, end_y: 2});
-        pin_ruy_lopez.do_move(Move{start_x: 6, start_y: 7, end_x: 5, end_y: 5});
-        pin_ruy_lopez.do_move(Move{start_x: 2, start_y: 0, end_x: 6, end_y: 4});
-        pin_ruy_lopez.do_move(Move{start_x: 4, start_y: 6, end_x: 4, end_y: 5});
-        pin_ruy_lopez.do_move(Move{start_x: 6, start_y: 0, end_x: 5, end_y: 2});
-        pin_ruy_lopez.do_move(Move{start_x: 5, start_y: 5, end_x: 4, end_y: 3});
-        pin_ruy_lopez.do_move(Move{start_x: 7, start_y: 6, end_x: 7, end_y: 5});
+        pin_ruy_lopez.do_move(Move::new(Square::E2, Square::E4)).unwrap();
+        pin_ruy_lopez.do_move(Move::new(Square::E7, Square::E5)).unwrap();
+        pin_ruy_lopez.do_move(Move::new(Square::G1, Square::F3)).unwrap();
+        pin_ruy_lopez.do_move(Move::new(Square::B8, Square::C6)).unwrap();
+        pin_ruy_lopez.do_move(Move::new(Square::F1, Square::B5)).unwrap();
+        pin_ruy_lopez.do_move(Move::new(Square::D7, Square::D6)).unwrap();
+        pin_ruy_lopez.do_move(Move::new(Square::B1, Square::C3)).unwrap();
+        assert_eq!(pin_ruy_lopez.do_move(Move::new(Square::C6, Square::D4)), Err(MoveError::SelfCheck));
+        pin_ruy_lopez.do_move(Move::new(Square::A7, Square::A6)).unwrap();
         let fen_game = Game::game_from_fen("r1bqkbnr/1pp2ppp/p1np4/1B2p3/4P3/2N2N2/PPPP1PPP/R1BQK2R w KQkq - 0 5");
-        assert_eq!(pin_ruy_lopez.board, fen_game.board);
+        assert_eq!(pin_ruy_lopez.board(), fen_game.board());
     }
     #[test]
     pub fn fools_mate() {
         let mut fools_mate = Game::new_game();
-        fools_mate.do_move(Move{start_x: 3, start_y: 1, end_x: 3, end_y: 3});
-        fools_mate.do_move(Move{start_x: 1, start_y: 6, end_x: 1, end_y: 4});
-        fools_mate.do_move(Move{start_x: 4, start_y: 1, end_x: 4, end_y: 3});
-        fools_mate.do_move(Move{start_x: 2, start_y: 6, end_x: 2, end_y: 5});
-        fools_mate.do_move(Move{start_x: 4, start_y: 0, end_x: 0, end_y: 4});
+        fools_mate.do_move(Move::new(Square::E2, Square::E4)).unwrap();
+        fools_mate.do_move(Move::new(Square::G7, Square::G5)).unwrap();
+        fools_mate.do_move(Move::new(Square::D2, Square::D4)).unwrap();
+        fools_mate.do_move(Move::new(Square::F7, Square::F6)).unwrap();
+        assert_eq!(fools_mate.do_move(Move::new(Square::D1, Square::H5)).unwrap().decision, Some(Decision::White));
+        assert_eq!(fools_mate.termination(), Some(Termination::Checkmate));
         let fen_game = Game::game_from_fen("rnbqkbnr/ppppp2p/5p2/6pQ/3PP3/8/PPP2PPP/RNB1KBNR b KQkq - 1 3");
-        assert_eq!(fools_mate.board, fen_game.board);
+        assert_eq!(fools_mate.board(), fen_game.board());
     }
     
     #[test]
     pub fn move_after_mate() {
         let mut move_after_mate = Game::new_game();
-        move_after_mate.do_move(Move{start_x: 3, start_y: 1, end_x: 3, end_y: 3});
-        move_after_mate.do_move(Move{start_x: 1, start_y: 6, end_x: 1, end_y: 4});
-        move_after_mate.do_move(Move{start_x: 4, start_y: 1, end_x: 4, end_y: 3});
-        move_after_mate.do_move(Move{start_x: 2, start_y: 6, end_x: 2, end_y: 5});
-        move_after_mate.do_move(Move{start_x: 4, start_y: 0, end_x: 0, end_y: 4});
-        move_after_mate.do_move(Move{start_x: 4, start_y: 0, end_x: 0, end_y: 4});
-        move_after_mate.do_move(Move{start_x: 4, start_y: 0, end_x: 0, end_y: 4});
+        move_after_mate.do_move(Move::new(Square::E2, Square::E4)).unwrap();
+        move_after_mate.do_move(Move::new(Square::G7, Square::G5)).unwrap();
+        move_after_mate.do_move(Move::new(Square::D2, Square::D4)).unwrap();
+        move_after_mate.do_move(Move::new(Square::F7, Square::F6)).unwrap();
+        move_after_mate.do_move(Move::new(Square::D1, Square::H5)).unwrap();
+        assert!(move_after_mate.do_move(Move::new(Square::D1, Square::H5)).is_err());
+        assert!(move_after_mate.do_move(Move::new(Square::D1, Square::H5)).is_err());
         let fen_game = Game::game_from_fen("rnbqkbnr/ppppp2p/5p2/6pQ/3PP3/8/PPP2PPP/RNB1KBNR b KQkq - 1 3");
-        assert_eq!(move_after_mate.board, fen_game.board);
+        assert_eq!(move_after_mate.board(), fen_game.board());
     }
 
     #[test]
     pub fn sic_queen() {
         let mut sic_queen = Game::new_game();
-        sic_queen.do_move(Move{start_x: 3, start_y: 1, end_x: 3, end_y: 3});
-        sic_queen.do_move(Move{start_x: 5, start_y: 6, end_x: 5, end_y: 4});
-        sic_queen.do_move(Move{start_x: 4, start_y: 1, end_x: 4, end_y: 3});
-        sic_queen.do_move(Move{start_x: 4, start_y: 7, end_x: 7, end_y: 4});
-        sic_queen.do_move(Move{start_x: 5, start_y: 1, end_x: 5, end_y: 2});
+        sic_queen.do_move(Move::new(Square::E2, Square::E4)).unwrap();
+        sic_queen.do_move(Move::new(Square::C7, Square::C5)).unwrap();
+        sic_queen.do_move(Move::new(Square::D2, Square::D4)).unwrap();
+        sic_queen.do_move(Move::new(Square::D8, Square::A5)).unwrap();
+        sic_queen.do_move(Move::new(Square::C2, Square::C3)).unwrap();
         let fen_game = Game::game_from_fen("rnb1kbnr/pp1ppppp/8/q1p5/3PP3/2P5/PP3PPP/RNBQKBNR b KQkq - 0 3");
-        assert_eq!(sic_queen.board, fen_game.board);
+        assert_eq!(sic_queen.board(), fen_game.board());
+    }
+
+    #[test]
+    pub fn castle_king_side() {
+        let mut castle = Game::game_from_fen("r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1");
+        castle.do_move(Move::new(Square::E1, Square::G1)).unwrap();
+        let fen_game = Game::game_from_fen("r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R4RK1 b kq - 1 1");
+        assert_eq!(castle.board(), fen_game.board());
+        assert_eq!(castle.castling, fen_game.castling);
+        castle.do_move(Move::new(Square::E8, Square::C8)).unwrap();
+        let fen_game = Game::game_from_fen("2kr3r/pppppppp/8/8/8/8/PPPPPPPP/R4RK1 w - - 2 2");
+        assert_eq!(castle.board(), fen_game.board());
+        assert_eq!(castle.castling, CastlingRights::none());
+    }
+
+    #[test]
+    pub fn castle_blocked() {
+        let mut castle = Game::game_from_fen("r3k2r/8/8/8/8/8/8/R3KB1R w KQkq - 0 1");
+        assert_eq!(castle.do_move(Move::new(Square::E1, Square::G1)), Err(MoveError::BlockedPath));
+        let mut castle = Game::game_from_fen("4kr2/8/8/8/8/8/8/R3K2R w KQ - 0 1");
+        assert_eq!(castle.do_move(Move::new(Square::E1, Square::G1)), Err(MoveError::CastleThroughCheck));
+        castle.do_move(Move::new(Square::E1, Square::C1)).unwrap();
+    }
+
+    #[test]
+    pub fn castling_rights_lost() {
+        let mut castle = Game::game_from_fen("r3k2r/7p/8/8/8/8/8/R3K2R b KQkq - 0 1");
+        castle.do_move(Move::new(Square::H7, Square::H6)).unwrap();
+        castle.do_move(Move::new(Square::A1, Square::A8)).unwrap();
+        assert_eq!(castle.castling, CastlingRights{white_king_side: true, white_queen_side: false, black_king_side: true, black_queen_side: false});
+        castle.do_move(Move::new(Square::E8, Square::D7)).unwrap();
+        castle.do_move(Move::new(Square::E1, Square::F1)).unwrap();
+        castle.do_move(Move::new(Square::D7, Square::D6)).unwrap();
+        castle.do_move(Move::new(Square::F1, Square::E1)).unwrap();
+        castle.do_move(Move::new(Square::D6, Square::D5)).unwrap();
+        assert_eq!(castle.do_move(Move::new(Square::E1, Square::G1)), Err(MoveError::Movement));
+    }
+
+    #[test]
+    pub fn en_passant() {
+        let mut en_passant = Game::game_from_fen("rnbqkbnr/pppppppp/8/4P3/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2");
+        en_passant.do_move(Move::new(Square::D7, Square::D5)).unwrap();
+        assert_eq!(en_passant.en_passant, Some(Square::D6));
+        en_passant.do_move(Move::new(Square::E5, Square::D6)).unwrap();
+        let fen_game = Game::game_from_fen("rnbqkbnr/ppp1pppp/3P4/8/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 3");
+        assert_eq!(en_passant.board(), fen_game.board());
+        assert_eq!(en_passant.en_passant, None);
+    }
+
+    #[test]
+    pub fn en_passant_expires() {
+        let mut en_passant = Game::game_from_fen("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3");
+        assert_eq!(en_passant.en_passant, Some(Square::D6));
+        en_passant.do_move(Move::new(Square::G1, Square::F3)).unwrap();
+        en_passant.do_move(Move::new(Square::G8, Square::F6)).unwrap();
+        assert_eq!(en_passant.do_move(Move::new(Square::E5, Square::D6)), Err(MoveError::Movement));
+    }
+
+    #[test]
+    pub fn en_passant_exposes_king() {
+        let mut en_passant = Game::game_from_fen("8/8/8/K2pP2r/8/8/8/7k w - d6 0 1");
+        assert_eq!(en_passant.do_move(Move::new(Square::E5, Square::D6)), Err(MoveError::SelfCheck));
+        assert_eq!(en_passant.board(), Game::game_from_fen("8/8/8/K2pP2r/8/8/8/7k w - d6 0 1").board());
+    }
+
+    #[test]
+    pub fn promotion() {
+        let mut promotion = Game::game_from_fen("1n5k/P7/8/8/8/8/8/K7 w - - 0 1");
+        assert_eq!(promotion.do_move(Move::new(Square::A7, Square::A8)), Err(MoveError::MissingPromotion));
+        assert_eq!(promotion.do_move(Move::promote(Square::A7, Square::A8, PieceType::King)), Err(MoveError::InvalidPromotion));
+        promotion.do_move(Move::promote(Square::A7, Square::B8, PieceType::Rook)).unwrap();
+        let fen_game = Game::game_from_fen("1R5k/8/8/8/8/8/8/K7 b - - 0 1");
+        assert_eq!(promotion.board(), fen_game.board());
+        assert_eq!(promotion.do_move(Move::promote(Square::H8, Square::G8, PieceType::Queen)), Err(MoveError::InvalidPromotion));
+    }
+
+    #[test]
+    pub fn stalemate() {
+        let mut stalemate = Game::game_from_fen("k7/8/2K5/8/8/8/8/1Q6 w - - 0 1");
+        assert_eq!(stalemate.do_move(Move::new(Square::B1, Square::B6)).unwrap().decision, Some(Decision::Tie));
+        assert_eq!(stalemate.termination(), Some(Termination::Stalemate));
+        assert_eq!(stalemate.do_move(Move::new(Square::A8, Square::A7)), Err(MoveError::Mated));
+    }
+
+    #[test]
+    pub fn legal_moves() {
+        let new_game = Game::new_game();
+        assert_eq!(new_game.legal_moves().len(), 20);
+        assert_eq!(new_game.legal_moves_from(Square::G1), vec![Move::new(Square::G1, Square::F3), Move::new(Square::G1, Square::H3)]);
+        assert!(new_game.legal_moves_from(Square::G8).is_empty());
+        let kiwipete = Game::game_from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        assert_eq!(kiwipete.legal_moves().len(), 48);
+        assert!(kiwipete.legal_moves_from(Square::E1).contains(&Move::new(Square::E1, Square::G1)));
+        assert!(kiwipete.legal_moves_from(Square::E1).contains(&Move::new(Square::E1, Square::C1)));
+        let promotions = Game::game_from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1");
+        assert_eq!(promotions.legal_moves().len(), 6);
+        let mut en_passant = Game::game_from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1");
+        assert_eq!(en_passant.legal_moves().len(), 14);
+        en_passant.do_move(Move::new(Square::E2, Square::E4)).unwrap();
+        // taking en passant would clear the whole rank between the rook and the king
+        assert!(!en_passant.legal_moves().contains(&Move::new(Square::F4, Square::E3)));
+        assert_eq!(en_passant.legal_moves().len(), 16);
+    }
+
+    #[test]
+    pub fn fen_round_trip() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 12 40",
+            "r3k2r/8/8/8/8/8/8/R3K2R w Kq - 0 1",
+        ] {
+            assert_eq!(Game::game_from_fen(fen).to_fen(), fen);
+        }
+    }
+
+    #[test]
+    pub fn fen_after_moves() {
+        let mut italian_game = Game::new_game();
+        italian_game.do_move(Move::new(Square::E2, Square::E4)).unwrap();
+        assert_eq!(italian_game.to_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+        italian_game.do_move(Move::new(Square::E7, Square::E5)).unwrap();
+        italian_game.do_move(Move::new(Square::G1, Square::F3)).unwrap();
+        italian_game.do_move(Move::new(Square::B8, Square::C6)).unwrap();
+        italian_game.do_move(Move::new(Square::F1, Square::C4)).unwrap();
+        assert_eq!(italian_game.to_fen(), "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3");
+    }
+    #[test]
+    pub fn undo_move() {
+        let fen = "r3k2r/8/8/8/3pP3/8/1p6/R3K2R b KQkq e3 0 1";
+        let mut undo = Game::game_from_fen(fen);
+        // en passant, castling on both sides and a promotion capturing a rook
+        let moves = [
+            Move::new(Square::D4, Square::E3),
+            Move::new(Square::E1, Square::G1),
+            Move::promote(Square::B2, Square::A1, PieceType::Queen),
+            Move::new(Square::G1, Square::G2),
+            Move::new(Square::E8, Square::C8),
+            Move::new(Square::F1, Square::F7),
+        ];
+        let mut fens = vec![undo.to_fen()];
+        for mv in moves {
+            undo.do_move(mv).unwrap();
+            fens.push(undo.to_fen());
+        }
+        for mv in moves.iter().rev() {
+            fens.pop();
+            assert_eq!(undo.undo_move(), Some(*mv));
+            assert_eq!(&undo.to_fen(), fens.last().unwrap());
+        }
+        assert_eq!(undo.undo_move(), None);
+        assert_eq!(undo.to_fen(), fen);
+    }
+
+    #[test]
+    pub fn redo_move() {
+        let mut redo = Game::new_game();
+        redo.do_move(Move::new(Square::E2, Square::E4)).unwrap();
+        redo.do_move(Move::new(Square::G7, Square::G5)).unwrap();
+        redo.do_move(Move::new(Square::D2, Square::D4)).unwrap();
+        redo.do_move(Move::new(Square::F7, Square::F6)).unwrap();
+        redo.do_move(Move::new(Square::D1, Square::H5)).unwrap();
+        let mated = redo.to_fen();
+        assert_eq!(redo.undo_move(), Some(Move::new(Square::D1, Square::H5)));
+        assert_eq!(redo.termination(), None);
+        assert_eq!(redo.undo_move(), Some(Move::new(Square::F7, Square::F6)));
+        assert_eq!(redo.redo_move(), Some(Move::new(Square::F7, Square::F6)));
+        assert_eq!(redo.redo_move(), Some(Move::new(Square::D1, Square::H5)));
+        assert_eq!(redo.redo_move(), None);
+        assert_eq!(redo.to_fen(), mated);
+        assert_eq!(redo.termination(), Some(Termination::Checkmate));
+        // playing a new move throws away the moves that could be redone
+        redo.undo_move();
+        redo.do_move(Move::new(Square::D1, Square::E2)).unwrap();
+        assert_eq!(redo.redo_move(), None);
+
+        // and so does claiming a draw
+        let mut claimed = Game::game_from_fen("k7/8/8/8/8/8/1R6/K7 w - - 99 80");
+        claimed.do_move(Move::new(Square::B2, Square::C2)).unwrap();
+        claimed.do_move(Move::new(Square::A8, Square::B8)).unwrap();
+        claimed.undo_move();
+        assert_eq!(claimed.claim_draw(), Some(Decision::Tie));
+        assert_eq!(claimed.redo_move(), None);
+    }
+
+    #[test]
+    pub fn fifty_move_rule() {
+        let mut fifty = Game::game_from_fen("k7/8/8/8/8/8/1R6/K7 w - - 98 80");
+        assert!(!fifty.can_claim_fifty_move_draw());
+        assert_eq!(fifty.claim_draw(), None);
+        fifty.do_move(Move::new(Square::B2, Square::C2)).unwrap();
+        assert!(!fifty.can_claim_fifty_move_draw());
+        fifty.do_move(Move::new(Square::A8, Square::B8)).unwrap();
+        assert!(fifty.can_claim_fifty_move_draw());
+        // the game goes on until someone claims it
+        fifty.do_move(Move::new(Square::C2, Square::D2)).unwrap();
+        assert_eq!(fifty.claim_draw(), Some(Decision::Tie));
+        assert_eq!(fifty.termination(), Some(Termination::FiftyMoveRule));
+        assert_eq!(fifty.do_move(Move::new(Square::B8, Square::A8)), Err(MoveError::Mated));
+    }
+
+    #[test]
+    pub fn seventy_five_move_rule() {
+        let mut seventy_five = Game::game_from_fen("k7/8/8/8/8/8/1R6/K7 w - - 148 80");
+        assert_eq!(seventy_five.do_move(Move::new(Square::B2, Square::C2)).unwrap().decision, None);
+        assert_eq!(seventy_five.do_move(Move::new(Square::A8, Square::B8)).unwrap().decision, Some(Decision::Tie));
+        assert_eq!(seventy_five.termination(), Some(Termination::SeventyFiveMoveRule));
+        // a capture resets the clock
+        let mut capture = Game::game_from_fen("k7/8/8/8/8/8/1r6/KR6 w - - 149 80");
+        assert_eq!(capture.do_move(Move::new(Square::B1, Square::B2)).unwrap().decision, None);
+        assert_eq!(capture.halfmove_clock, 0);
+    }
+
+    #[test]
+    pub fn repetition() {
+        let mut repetition = Game::new_game();
+        let knights = [Move::new(Square::G1, Square::F3), Move::new(Square::G8, Square::F6), Move::new(Square::F3, Square::G1), Move::new(Square::F6, Square::G8)];
+        for mv in knights {
+            repetition.do_move(mv).unwrap();
+        }
+        assert!(!repetition.can_claim_threefold());
+        for mv in knights {
+            repetition.do_move(mv).unwrap();
+        }
+        assert!(repetition.can_claim_threefold());
+        // the position after the first knight move has come up three times as well
+        repetition.do_move(knights[0]).unwrap();
+        assert!(repetition.can_claim_threefold());
+        for mv in &knights[1..] {
+            repetition.do_move(*mv).unwrap();
+        }
+        assert_eq!(repetition.claim_draw(), Some(Decision::Tie));
+        assert_eq!(repetition.termination(), Some(Termination::ThreefoldRepetition));
+        // without claiming, the game goes on until the fifth time
+        repetition.undo_move();
+        repetition.do_move(knights[3]).unwrap();
+        for mv in &knights[..3] {
+            assert_eq!(repetition.do_move(*mv).unwrap().decision, None);
+        }
+        assert_eq!(repetition.do_move(knights[3]).unwrap().decision, Some(Decision::Tie));
+        assert_eq!(repetition.termination(), Some(Termination::FivefoldRepetition));
+    }
+
+    #[test]
+    pub fn repetition_en_passant() {
+        // after the double pawn move en passant is possible, so the position isn't the same as later on
+        let mut repetition = Game::game_from_fen("4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1");
+        repetition.do_move(Move::new(Square::E2, Square::E4)).unwrap();
+        let moves = [Move::new(Square::E8, Square::D8), Move::new(Square::E1, Square::D1), Move::new(Square::D8, Square::E8), Move::new(Square::D1, Square::E1)];
+        for mv in moves.iter().chain(moves.iter()) {
+            repetition.do_move(*mv).unwrap();
+        }
+        assert!(!repetition.can_claim_threefold());
+        for mv in moves {
+            repetition.do_move(mv).unwrap();
+        }
+        assert!(repetition.can_claim_threefold());
+    }
+
+    #[test]
+    pub fn insufficient_material() {
+        let insufficient = |fen: &str, color: Color| Game::game_from_fen(fen).is_insufficient_material(color);
+        assert!(insufficient("k7/8/8/8/8/8/8/K7 w - - 0 1", Color::White));
+        assert!(insufficient("k7/8/8/8/8/8/8/KN6 w - - 0 1", Color::White));
+        assert!(insufficient("kq6/8/8/8/8/8/8/KN6 w - - 0 1", Color::White));
+        assert!(!insufficient("kn6/8/8/8/8/8/8/KN6 w - - 0 1", Color::White));
+        assert!(insufficient("kb6/8/8/8/8/8/8/K1B5 w - - 0 1", Color::White));
+        assert!(!insufficient("k1b5/8/8/8/8/8/8/K1B5 w - - 0 1", Color::White));
+        assert!(!insufficient("k7/8/8/8/8/8/8/KBB5 w - - 0 1", Color::White));
+        assert!(!insufficient("k7/8/8/8/8/8/8/KNN5 w - - 0 1", Color::White));
+        assert!(!insufficient("k7/8/8/8/8/8/1P6/K7 w - - 0 1", Color::White));
+        assert!(insufficient("k7/8/8/8/8/8/1P6/K7 w - - 0 1", Color::Black));
+    }
+
+    #[test]
+    pub fn dead_position() {
+        let mut dead = Game::game_from_fen("k7/8/8/8/8/8/1r6/KN6 w - - 0 1");
+        assert_eq!(dead.do_move(Move::new(Square::A1, Square::B2)).unwrap().decision, Some(Decision::Tie));
+        assert_eq!(dead.termination(), Some(Termination::InsufficientMaterial));
+        let mut alive = Game::game_from_fen("k7/8/8/8/8/8/1r6/KN5n w - - 0 1");
+        assert_eq!(alive.do_move(Move::new(Square::A1, Square::B2)).unwrap().decision, None);
     }
 }
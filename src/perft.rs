@@ -0,0 +1,94 @@
+use crate::{Game, Move};
+
+impl Game {
+    // counts the positions reached after every sequence of `depth` legal moves, the usual way of
+    // checking a move generator against known numbers. draws by repetition or the move clocks
+    // don't stop the count, like in other tools
+    pub fn perft(&self, depth: u32) -> u64 {
+        let mut game = self.clone();
+        // a finished game still has its moves counted, perft only cares about how pieces move
+        game.finished = false;
+        game.perft_nodes(depth)
+    }
+
+    // perft split up by the first move. when the total is off, comparing this with another
+    // tool's output shows which move goes wrong:
+    //
+    //     for (mv, nodes) in game.perft_divide(3) {
+    //         println!("{}: {}", mv, nodes);
+    //     }
+    //
+    // at depth 0 no move is played, so there is nothing to split up and the list is empty
+    pub fn perft_divide(&self, depth: u32) -> Vec<(Move, u64)> {
+        if depth == 0 {
+            return Vec::new();
+        }
+        let mut game = self.clone();
+        game.finished = false;
+        let moves = game.legal_moves();
+        moves.into_iter().map(|mv| {
+            let record = game.make_move(mv);
+            let nodes = game.perft_nodes(depth - 1);
+            game.unmake_move(&record);
+            (mv, nodes)
+        }).collect()
+    }
+
+    fn perft_nodes(&mut self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let mut moves = Vec::new();
+        self.generate_moves(self.colors[self.turn as usize], &mut moves);
+        // the moves themselves are the positions one move ahead, no need to play them
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+        let mut nodes = 0;
+        for mv in moves {
+            let record = self.make_move(mv);
+            nodes += self.perft_nodes(depth - 1);
+            self.unmake_move(&record);
+        }
+        nodes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the standard perft positions, with their known node counts by depth
+    const POSITIONS: [(&str, &[u64]); 6] = [
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", &[20, 400, 8902, 197281]),
+        ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", &[48, 2039, 97862]),
+        ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", &[14, 191, 2812, 43238, 674624]),
+        ("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", &[6, 264, 9467, 422333]),
+        ("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", &[44, 1486, 62379]),
+        ("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10", &[46, 2079, 89890]),
+    ];
+
+    #[test]
+    pub fn perft_positions() {
+        for (fen, counts) in POSITIONS {
+            let game = Game::game_from_fen(fen);
+            for (depth, &count) in counts.iter().enumerate() {
+                assert_eq!(game.perft(depth as u32 + 1), count, "{} at depth {}", fen, depth + 1);
+            }
+        }
+        assert_eq!(Game::new_game().perft(0), 1);
+    }
+
+    #[test]
+    pub fn perft_divide() {
+        let divide = Game::new_game().perft_divide(3);
+        assert_eq!(divide.len(), 20);
+        assert_eq!(divide.iter().map(|(_, nodes)| nodes).sum::<u64>(), 8902);
+        let lines: Vec<String> = divide.iter().map(|(mv, nodes)| format!("{}: {}", mv, nodes)).collect();
+        assert!(lines.contains(&"e2e4: 600".to_string()));
+        assert!(lines.contains(&"g1f3: 440".to_string()));
+        // each move is one position at depth 1, and there are no moves to split by at depth 0
+        assert!(Game::new_game().perft_divide(1).iter().all(|&(_, nodes)| nodes == 1));
+        assert!(Game::new_game().perft_divide(0).is_empty());
+    }
+}
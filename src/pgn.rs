@@ -0,0 +1,527 @@
+use std::fmt;
+use std::io::{self, BufRead};
+
+use crate::{Color, Decision, FenError, Game, SanError};
+
+// what to do with the parts of the movetext that don't change the game. anything that isn't
+// skipped is reported as an error instead
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PgnOptions {
+    pub skip_comments: bool,
+    pub skip_nags: bool,
+    pub skip_variations: bool,
+}
+
+impl Default for PgnOptions {
+    fn default() -> PgnOptions {
+        PgnOptions { skip_comments: true, skip_nags: true, skip_variations: true }
+    }
+}
+
+// a game read from PGN, with the moves played on `game`
+#[derive(Clone, PartialEq, Debug)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub game: Game,
+    // "1-0", "0-1", "1/2-1/2", or "*" when unknown or missing
+    pub result: String,
+}
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum PgnErrorKind {
+    Io(io::ErrorKind),
+    MalformedTag,
+    InvalidFen(FenError),
+    UnterminatedComment,
+    UnterminatedVariation,
+    UnexpectedCharacter(char),
+    // found while told not to skip them
+    Comment,
+    Nag,
+    Variation,
+    // the move couldn't be read or played
+    Move(SanError),
+    // the game had already ended before the move
+    GameOver,
+}
+
+// which game of the file went wrong (counting from 1), and for move errors the ply (also counting
+// from 1) and the move as it was written
+#[derive(Clone, PartialEq, Debug)]
+pub struct PgnError {
+    pub game: usize,
+    pub ply: Option<usize>,
+    pub san: Option<String>,
+    pub kind: PgnErrorKind,
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "game {}", self.game)?;
+        if let (Some(ply), Some(san)) = (self.ply, &self.san) {
+            write!(f, ", ply {} ({})", ply, san)?;
+        }
+        match &self.kind {
+            PgnErrorKind::Io(kind) => write!(f, ": read error: {}", kind),
+            PgnErrorKind::MalformedTag => write!(f, ": malformed tag pair"),
+            PgnErrorKind::InvalidFen(e) => write!(f, ": invalid FEN tag: {}", e),
+            PgnErrorKind::UnterminatedComment => write!(f, ": comment is never closed"),
+            PgnErrorKind::UnterminatedVariation => write!(f, ": variation is never closed"),
+            PgnErrorKind::UnexpectedCharacter(c) => write!(f, ": unexpected character '{}'", c),
+            PgnErrorKind::Comment => write!(f, ": comments are not allowed"),
+            PgnErrorKind::Nag => write!(f, ": NAGs are not allowed"),
+            PgnErrorKind::Variation => write!(f, ": variations are not allowed"),
+            PgnErrorKind::Move(e) => write!(f, ": {}", e),
+            PgnErrorKind::GameOver => write!(f, ": the game has already ended"),
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+// reads the games of a PGN file one at a time, only reading as far into the file as needed
+//
+//     for game in PgnReader::new(BufReader::new(File::open("games.pgn")?)) { ... }
+//
+// a &str can be read with PgnReader::new(text.as_bytes())
+pub struct PgnReader<R> {
+    lines: io::Lines<R>,
+    line: Vec<char>,
+    pos: usize,
+    games: usize,
+    options: PgnOptions,
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(reader: R) -> PgnReader<R> {
+        PgnReader::with_options(reader, PgnOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: PgnOptions) -> PgnReader<R> {
+        PgnReader { lines: reader.lines(), line: Vec::new(), pos: 0, games: 0, options }
+    }
+
+    fn peek(&mut self) -> io::Result<Option<char>> {
+        while self.pos >= self.line.len() {
+            let line = match self.lines.next() {
+                Some(line) => line?,
+                None => return Ok(None),
+            };
+            // a "%" at the start of a line escapes the whole line
+            if line.starts_with('%') {
+                continue;
+            }
+            self.line = line.chars().collect();
+            self.line.push('\n');
+            self.pos = 0;
+        }
+        Ok(Some(self.line[self.pos]))
+    }
+
+    fn next_char(&mut self) -> io::Result<Option<char>> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Ok(c)
+    }
+
+    fn skip_whitespace(&mut self) -> io::Result<Option<char>> {
+        while let Some(c) = self.peek()? {
+            if !c.is_whitespace() {
+                return Ok(Some(c));
+            }
+            self.pos += 1;
+        }
+        Ok(None)
+    }
+
+    // [Name "value"]
+    fn read_tag(&mut self) -> Result<(String, String), PgnErrorKind> {
+        self.pos += 1;
+        let mut name = String::new();
+        self.skip_whitespace().map_err(io_error)?;
+        while let Some(c) = self.peek().map_err(io_error)? {
+            if !(c.is_alphanumeric() || c == '_') {
+                break;
+            }
+            name.push(c);
+            self.pos += 1;
+        }
+        if name.is_empty() || self.skip_whitespace().map_err(io_error)? != Some('"') {
+            self.skip_rest_of_line();
+            return Err(PgnErrorKind::MalformedTag);
+        }
+        self.pos += 1;
+        let mut value = String::new();
+        loop {
+            match self.next_char().map_err(io_error)? {
+                Some('"') => break,
+                Some('\\') => {
+                    if let Some(c) = self.next_char().map_err(io_error)? {
+                        value.push(c);
+                    }
+                }
+                Some('\n') | None => return Err(PgnErrorKind::MalformedTag),
+                Some(c) => value.push(c),
+            }
+        }
+        if self.skip_whitespace().map_err(io_error)? != Some(']') {
+            self.skip_rest_of_line();
+            return Err(PgnErrorKind::MalformedTag);
+        }
+        self.pos += 1;
+        Ok((name, value))
+    }
+
+    fn skip_rest_of_line(&mut self) {
+        self.pos = self.line.len();
+    }
+
+    // { ... }, which can't be nested
+    fn skip_comment(&mut self) -> Result<(), PgnErrorKind> {
+        self.pos += 1;
+        loop {
+            match self.next_char().map_err(io_error)? {
+                Some('}') => return Ok(()),
+                Some(_) => {},
+                None => return Err(PgnErrorKind::UnterminatedComment),
+            }
+        }
+    }
+
+    // ( ... ), which can hold comments and other variations
+    fn skip_variation(&mut self) -> Result<(), PgnErrorKind> {
+        let mut depth = 0;
+        loop {
+            match self.peek().map_err(io_error)? {
+                Some('(') => depth += 1,
+                Some(')') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.pos += 1;
+                        return Ok(());
+                    }
+                }
+                Some('{') => {
+                    self.skip_comment()?;
+                    continue;
+                }
+                Some(';') => {
+                    self.skip_rest_of_line();
+                    continue;
+                }
+                Some(_) => {},
+                None => return Err(PgnErrorKind::UnterminatedVariation),
+            }
+            self.pos += 1;
+        }
+    }
+
+    // a move, move number, NAG or result, up to the next space or special character
+    fn read_token(&mut self) -> io::Result<String> {
+        let mut token = String::new();
+        while let Some(c) = self.peek()? {
+            if c.is_whitespace() || "{}()[];".contains(c) {
+                break;
+            }
+            token.push(c);
+            self.pos += 1;
+        }
+        Ok(token)
+    }
+
+    fn read_game(&mut self) -> Option<Result<PgnGame, PgnError>> {
+        self.games += 1;
+        let game_number = self.games;
+        let error = |kind| PgnError { game: game_number, ply: None, san: None, kind };
+        // the first problem found is reported, but the rest of the game is still read so that the
+        // next game starts at the right place
+        let mut first_error: Option<PgnError> = None;
+
+        let mut tags = Vec::new();
+        loop {
+            match self.skip_whitespace() {
+                Ok(None) if tags.is_empty() => return None,
+                Ok(Some('[')) => match self.read_tag() {
+                    Ok(tag) => tags.push(tag),
+                    Err(kind) => {
+                        first_error.get_or_insert(error(kind));
+                    }
+                },
+                Ok(_) => break,
+                Err(e) => return Some(Err(error(io_error(e)))),
+            }
+        }
+
+        let mut game = Game::new_game();
+        let set_up = tags.iter().find(|(name, _)| name == "SetUp").map(|(_, value)| value.as_str());
+        if let Some((_, fen)) = tags.iter().find(|(name, _)| name == "FEN") {
+            if set_up != Some("0") {
+                match Game::from_fen(fen) {
+                    Ok(g) => game = g,
+                    Err(e) => {
+                        first_error.get_or_insert(error(PgnErrorKind::InvalidFen(e)));
+                    }
+                }
+            }
+        }
+
+        let mut result = String::from("*");
+        let mut ply = 0;
+        loop {
+            let c = match self.skip_whitespace() {
+                Ok(Some(c)) => c,
+                // the next game's tags, or the end of the file, without a result
+                Ok(None) => break,
+                Err(e) => return Some(Err(error(io_error(e)))),
+            };
+            let skipped = match c {
+                '[' => break,
+                '{' => self.skip_comment().and(if self.options.skip_comments { Ok(()) } else { Err(PgnErrorKind::Comment) }),
+                ';' => {
+                    self.skip_rest_of_line();
+                    if self.options.skip_comments { Ok(()) } else { Err(PgnErrorKind::Comment) }
+                }
+                '(' => self.skip_variation().and(if self.options.skip_variations { Ok(()) } else { Err(PgnErrorKind::Variation) }),
+                '$' => {
+                    self.pos += 1;
+                    match self.read_token() {
+                        Ok(_) if self.options.skip_nags => Ok(()),
+                        Ok(_) => Err(PgnErrorKind::Nag),
+                        Err(e) => Err(io_error(e)),
+                    }
+                }
+                ')' | ']' | '}' => {
+                    self.pos += 1;
+                    Err(PgnErrorKind::UnexpectedCharacter(c))
+                }
+                _ => Ok(()),
+            };
+            if let Err(kind) = skipped {
+                first_error.get_or_insert(error(kind));
+            }
+            if "[{;($)]}".contains(c) {
+                continue;
+            }
+
+            let token = match self.read_token() {
+                Ok(token) => token,
+                Err(e) => return Some(Err(error(io_error(e)))),
+            };
+            if ["1-0", "0-1", "1/2-1/2", "*"].contains(&token.as_str()) {
+                result = token;
+                break;
+            }
+            // move numbers like "12." or "12...", possibly written right before the move. the
+            // digits need the dot, castling is sometimes written with zeros as "0-0"
+            let after_digits = token.trim_start_matches(|c: char| c.is_ascii_digit());
+            let san = if after_digits.is_empty() || after_digits.starts_with('.') {
+                after_digits.trim_start_matches('.')
+            }
+            else {
+                token.as_str()
+            };
+            if san.is_empty() || first_error.is_some() {
+                continue;
+            }
+            ply += 1;
+            let kind = if game.termination().is_some() {
+                Some(PgnErrorKind::GameOver)
+            }
+            else {
+                match game.parse_san(san) {
+                    Ok(mv) => game.do_move(mv).err().map(|_| PgnErrorKind::GameOver),
+                    Err(e) => Some(PgnErrorKind::Move(e)),
+                }
+            };
+            if let Some(kind) = kind {
+                first_error = Some(PgnError { game: game_number, ply: Some(ply), san: Some(san.to_string()), kind });
+            }
+        }
+
+        Some(match first_error {
+            Some(e) => Err(e),
+            None => Ok(PgnGame { tags, game, result }),
+        })
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<PgnGame, PgnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_game()
+    }
+}
+
+fn io_error(e: io::Error) -> PgnErrorKind {
+    PgnErrorKind::Io(e.kind())
+}
+
+// the tags every PGN game starts with, in this order
+const SEVEN_TAG_ROSTER: [&str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+impl Game {
+    // writes the game as PGN. the Seven Tag Roster is filled in from `tags` (with "?" for the
+    // ones left out) and any other tags follow it. the result always comes from decision(), and
+    // SetUp/FEN tags are added when the game didn't start from the standard position
+    //
+    //     game.to_pgn(&[("White", "Carlsen"), ("Black", "Nepomniachtchi"), ("TimeControl", "40/7200")])
+    pub fn to_pgn(&self, tags: &[(&str, &str)]) -> String {
+        let result = match self.decision() {
+            Some(Decision::White) => "1-0",
+            Some(Decision::Black) => "0-1",
+            Some(Decision::Tie) => "1/2-1/2",
+            None => "*",
+        };
+        let mut start = self.clone();
+        while start.undo_move().is_some() {}
+
+        let mut pgn = String::new();
+        for name in SEVEN_TAG_ROSTER {
+            let value = match name {
+                "Result" => result,
+                _ => match tags.iter().find(|(tag, _)| *tag == name) {
+                    Some((_, value)) => value,
+                    None if name == "Date" => "????.??.??",
+                    None => "?",
+                },
+            };
+            push_tag(&mut pgn, name, value);
+        }
+        let start_fen = start.to_fen();
+        if start_fen != Game::new_game().to_fen() {
+            push_tag(&mut pgn, "SetUp", "1");
+            push_tag(&mut pgn, "FEN", &start_fen);
+        }
+        for (name, value) in tags {
+            if !SEVEN_TAG_ROSTER.contains(name) && *name != "SetUp" && *name != "FEN" {
+                push_tag(&mut pgn, name, value);
+            }
+        }
+        pgn.push('\n');
+
+        let mut tokens = Vec::new();
+        for (i, record) in self.move_history.iter().enumerate() {
+            if start.turn == Color::White {
+                tokens.push(format!("{}.", start.fullmove_number));
+            }
+            else if i == 0 {
+                tokens.push(format!("{}...", start.fullmove_number));
+            }
+            tokens.push(start.to_san(&record.mv));
+            let _ = start.do_move(record.mv);
+        }
+        tokens.push(result.to_string());
+
+        // lines are wrapped to stay within 80 columns
+        let mut line_length = 0;
+        for token in tokens {
+            if line_length > 0 && line_length + 1 + token.len() > 80 {
+                pgn.push('\n');
+                line_length = 0;
+            }
+            if line_length > 0 {
+                pgn.push(' ');
+                line_length += 1;
+            }
+            pgn.push_str(&token);
+            line_length += token.len();
+        }
+        pgn.push('\n');
+        pgn
+    }
+}
+
+fn push_tag(pgn: &mut String, name: &str, value: &str) {
+    let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+    pgn.push_str(&format!("[{} \"{}\"]\n", name, value));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Termination;
+
+    const GAMES: &str = r#"[Event "Casual game"]
+[White "Anderssen"]
+[Black "Kieseritzky"]
+[Result "1-0"]
+
+1.e4 e5 2.f4 exf4 3.Bc4 Qh4+ {an early queen check} 4.Kf1 b5 5.Bxb5 Nf6 6.Nf3 Qh6
+7.d3 Nh5 8.Nh4 Qg5 9.Nf5 c6 10.g4 Nf6 11.Rg1 cxb5 12.h4 Qg6 13.h5 Qg5 14.Qf3 Ng8
+15.Bxf4 Qf6 16.Nc3 Bc5 17.Nd5 Qxb2 18.Bd6 Bxg1 $1 (18...Qxa1+ 19.Ke2 Qb2 (19...Qxg1)) 19.e5 Qxa1+
+20.Ke2 Na6 21.Nxg7+ Kd8 22.Qf6+ Nxf6 23.Be7# 1-0
+
+[Event "From a position"]
+[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"]
+
+1. e4 Kd7 ; resigns soon
+2. Kd2 *
+"#;
+
+    #[test]
+    pub fn read_games() {
+        let mut reader = PgnReader::new(GAMES.as_bytes());
+        let immortal = reader.next().unwrap().unwrap();
+        assert_eq!(immortal.tag("White"), Some("Anderssen"));
+        assert_eq!(immortal.result, "1-0");
+        assert_eq!(immortal.game.termination(), Some(Termination::Checkmate));
+        assert_eq!(immortal.game.to_fen(), "r1bk3r/p2pBpNp/n4n2/1p1NP2P/6P1/3P4/P1P1K3/q5b1 b - - 1 23");
+
+        let set_up = reader.next().unwrap().unwrap();
+        assert_eq!(set_up.result, "*");
+        assert_eq!(set_up.game.to_fen(), "8/3k4/8/8/4P3/8/3K4/8 b - - 2 2");
+        assert!(reader.next().is_none());
+
+        // castling written with zeros, and move numbers right before the move
+        let castled = PgnReader::new("1.e4 e5 2.Nf3 Nc6 3.Bc4 Bc5 4.0-0 Nf6 5.d3 0-0 *".as_bytes()).next().unwrap().unwrap();
+        assert_eq!(castled.game.to_fen(), "r1bq1rk1/pppp1ppp/2n2n2/2b1p3/2B1P3/3P1N2/PPP2PPP/RNBQ1RK1 w - - 1 6");
+    }
+
+    #[test]
+    pub fn read_errors() {
+        let pgn = "1. e4 e5 2. Ke3 Nc6 1-0\n\n1. d4 d5 2. Nf3 *\n";
+        let mut reader = PgnReader::new(pgn.as_bytes());
+        let e = reader.next().unwrap().unwrap_err();
+        assert_eq!((e.game, e.ply, e.san.as_deref()), (1, Some(3), Some("Ke3")));
+        assert_eq!(e.kind, PgnErrorKind::Move(SanError::Illegal));
+        // the next game is still read
+        assert_eq!(reader.next().unwrap().unwrap().game.to_fen(), "rnbqkbnr/ppp1pppp/8/3p4/3P4/5N2/PPP1PPPP/RNBQKB1R b KQkq - 1 2");
+
+        let options = PgnOptions { skip_variations: false, ..PgnOptions::default() };
+        let mut reader = PgnReader::with_options(GAMES.as_bytes(), options);
+        assert_eq!(reader.next().unwrap().unwrap_err().kind, PgnErrorKind::Variation);
+        assert!(reader.next().unwrap().is_ok());
+
+        let e = PgnReader::new("[FEN \"8/8/8 w - - 0 1\"]\n*".as_bytes()).next().unwrap().unwrap_err();
+        assert!(matches!(e.kind, PgnErrorKind::InvalidFen(_)));
+        let e = PgnReader::new("1. e4 { never closed".as_bytes()).next().unwrap().unwrap_err();
+        assert_eq!(e.kind, PgnErrorKind::UnterminatedComment);
+    }
+
+    #[test]
+    pub fn write_games() {
+        let immortal = PgnReader::new(GAMES.as_bytes()).next().unwrap().unwrap().game;
+        let pgn = immortal.to_pgn(&[("White", "Anderssen"), ("Black", "Kieseritzky"), ("Site", "London \"Simpson's\"")]);
+        assert!(pgn.starts_with("[Event \"?\"]\n[Site \"London \\\"Simpson's\\\"\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n[White \"Anderssen\"]\n[Black \"Kieseritzky\"]\n[Result \"1-0\"]\n\n1. e4 e5 2. f4 exf4 3. Bc4 Qh4+ 4. Kf1 b5"));
+        assert!(pgn.ends_with("23. Be7# 1-0\n"));
+        assert!(pgn.lines().all(|line| line.len() <= 80));
+
+        // reading it back gives the same game and tags
+        let read = PgnReader::new(pgn.as_bytes()).next().unwrap().unwrap();
+        assert_eq!(read.game, immortal);
+        assert_eq!(read.tag("Site"), Some("London \"Simpson's\""));
+
+        let mut set_up = Game::game_from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 30");
+        set_up.do_move(set_up.parse_san("Kd7").unwrap()).unwrap();
+        set_up.do_move(set_up.parse_san("e4").unwrap()).unwrap();
+        assert_eq!(set_up.to_pgn(&[("Annotator", "me")]), "[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n[White \"?\"]\n[Black \"?\"]\n[Result \"*\"]\n[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 30\"]\n[Annotator \"me\"]\n\n30... Kd7 31. e4 *\n");
+    }
+}
@@ -0,0 +1,250 @@
+use std::fmt;
+
+use crate::{Game, Move, PieceType, Square, Termination};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SanError {
+    // not something that can be read as a move at all
+    Malformed,
+    // no legal move in the position matches
+    Illegal,
+    // more than one legal move matches
+    Ambiguous,
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SanError::Malformed => write!(f, "malformed move"),
+            SanError::Illegal => write!(f, "illegal move"),
+            SanError::Ambiguous => write!(f, "ambiguous move"),
+        }
+    }
+}
+
+impl std::error::Error for SanError {}
+
+impl Game {
+    // reads a move in standard algebraic notation, like "Nbd7", "exd5", "O-O" or "e8=Q+", and
+    // finds the legal move it means in the current position
+    pub fn parse_san(&self, san: &str) -> Result<Move, SanError> {
+        // check and mate markers, and annotations like "!?", don't change which move it is
+        let san = san.trim().trim_end_matches(['+', '#', '!', '?']);
+        if san == "O-O" || san == "0-0" || san == "O-O-O" || san == "0-0-0" {
+            let king_side = san.len() == 3;
+            return self.only_match(self.legal_moves().into_iter().filter(|mv| {
+                Game::is_castling(mv, &self.piece_at(mv.from)) && (mv.to.file() > mv.from.file()) == king_side
+            }));
+        }
+
+        let mut chars: Vec<char> = san.chars().collect();
+        let piece = match chars.first().and_then(|&c| piece_from_letter(c)) {
+            Some(piece) => {
+                chars.remove(0);
+                piece
+            }
+            None => PieceType::Pawn,
+        };
+        // "e8=Q", though "e8Q" is seen often enough as well
+        let mut promotion = None;
+        if let Some(p) = chars.last().and_then(|&c| piece_from_letter(c)) {
+            if piece != PieceType::Pawn || p == PieceType::King {
+                return Err(SanError::Malformed);
+            }
+            promotion = Some(p);
+            chars.pop();
+            if chars.last() == Some(&'=') {
+                chars.pop();
+            }
+        }
+        if chars.len() < 2 {
+            return Err(SanError::Malformed);
+        }
+        let destination: String = chars.split_off(chars.len() - 2).into_iter().collect();
+        let destination: Square = destination.parse().map_err(|_| SanError::Malformed)?;
+        if chars.last() == Some(&'x') {
+            chars.pop();
+        }
+        // whatever is left tells which of several pieces is meant
+        let mut from_file = None;
+        let mut from_rank = None;
+        for c in chars {
+            match c {
+                'a'..='h' if from_file.is_none() && from_rank.is_none() => from_file = Some(c as usize - 'a' as usize),
+                '1'..='8' if from_rank.is_none() => from_rank = Some(c as usize - '1' as usize),
+                _ => return Err(SanError::Malformed),
+            }
+        }
+
+        self.only_match(self.legal_moves().into_iter().filter(|mv| {
+            self.piece_at(mv.from).map(|p| p.piece) == Some(piece)
+                && mv.to == destination
+                && mv.promotion == promotion
+                && from_file.is_none_or(|file| file == mv.from.file())
+                && from_rank.is_none_or(|rank| rank == mv.from.rank())
+                // a king moving two squares has to be written as castling
+                && !Game::is_castling(mv, &self.piece_at(mv.from))
+        }))
+    }
+
+    // writes a legal move in standard algebraic notation, with only as much disambiguation as
+    // needed and a "+" or "#" for check and mate
+    pub fn to_san(&self, mv: &Move) -> String {
+        let the_piece = match self.piece_at(mv.from) {
+            Some(p) => p,
+            None => return String::new(),
+        };
+        let mut san = String::new();
+        if Game::is_castling(mv, &Some(the_piece)) {
+            san.push_str(if mv.to.file() > mv.from.file() { "O-O" } else { "O-O-O" });
+        }
+        else {
+            let capture = self.piece_at(mv.to).is_some() || Game::is_en_passant(mv, &Some(the_piece), &None);
+            let file = mv.from.to_string().remove(0);
+            if the_piece.piece == PieceType::Pawn {
+                if capture {
+                    san.push(file);
+                }
+            }
+            else {
+                san.push(piece_letter(the_piece.piece));
+                // other pieces of the same kind that could go to the same square
+                let others: Vec<Move> = self.legal_moves().into_iter().filter(|other| {
+                    other.to == mv.to && other.from != mv.from && self.piece_at(other.from) == Some(the_piece)
+                }).collect();
+                if !others.is_empty() {
+                    if others.iter().all(|other| other.from.file() != mv.from.file()) {
+                        san.push(file);
+                    }
+                    else if others.iter().all(|other| other.from.rank() != mv.from.rank()) {
+                        san.push_str(&(mv.from.rank() + 1).to_string());
+                    }
+                    else {
+                        san.push_str(&mv.from.to_string());
+                    }
+                }
+            }
+            if capture {
+                san.push('x');
+            }
+            san.push_str(&mv.to.to_string());
+            if let Some(p) = mv.promotion {
+                san.push('=');
+                san.push(piece_letter(p));
+            }
+        }
+
+        let mut after = self.clone();
+        if after.do_move(*mv).is_ok() {
+            if after.termination() == Some(Termination::Checkmate) {
+                san.push('#');
+            }
+            else if after.in_check() {
+                san.push('+');
+            }
+        }
+        san
+    }
+
+    fn only_match(&self, mut moves: impl Iterator<Item = Move>) -> Result<Move, SanError> {
+        match (moves.next(), moves.next()) {
+            (Some(mv), None) => Ok(mv),
+            (Some(_), Some(_)) => Err(SanError::Ambiguous),
+            (None, _) => Err(SanError::Illegal),
+        }
+    }
+}
+
+fn piece_from_letter(c: char) -> Option<PieceType> {
+    match c {
+        'N' => Some(PieceType::Knight),
+        'B' => Some(PieceType::Bishop),
+        'R' => Some(PieceType::Rook),
+        'Q' => Some(PieceType::Queen),
+        'K' => Some(PieceType::King),
+        _ => None,
+    }
+}
+
+pub(crate) fn piece_letter(piece: PieceType) -> char {
+    match piece {
+        PieceType::Pawn => 'P',
+        PieceType::Knight => 'N',
+        PieceType::Bishop => 'B',
+        PieceType::Rook => 'R',
+        PieceType::Queen => 'Q',
+        PieceType::King => 'K',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn parse_san() {
+        let new_game = Game::new_game();
+        assert_eq!(new_game.parse_san("e4"), Ok(Move::new(Square::E2, Square::E4)));
+        assert_eq!(new_game.parse_san("Nf3"), Ok(Move::new(Square::G1, Square::F3)));
+        assert_eq!(new_game.parse_san("Ng1f3!?"), Ok(Move::new(Square::G1, Square::F3)));
+        assert_eq!(new_game.parse_san("e5"), Err(SanError::Illegal));
+        assert_eq!(new_game.parse_san("Nf4"), Err(SanError::Illegal));
+        assert_eq!(new_game.parse_san("hello"), Err(SanError::Malformed));
+        assert_eq!(new_game.parse_san(""), Err(SanError::Malformed));
+
+        let kiwipete = Game::game_from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        assert_eq!(kiwipete.parse_san("O-O"), Ok(Move::new(Square::E1, Square::G1)));
+        assert_eq!(kiwipete.parse_san("O-O-O"), Ok(Move::new(Square::E1, Square::C1)));
+        assert_eq!(kiwipete.parse_san("0-0"), Ok(Move::new(Square::E1, Square::G1)));
+        assert_eq!(kiwipete.parse_san("0-0-0+"), Ok(Move::new(Square::E1, Square::C1)));
+        assert_eq!(kiwipete.parse_san("Kg1"), Err(SanError::Illegal));
+        assert_eq!(kiwipete.parse_san("dxe6"), Ok(Move::new(Square::D5, Square::E6)));
+        assert_eq!(kiwipete.parse_san("Qxf6"), Ok(Move::new(Square::F3, Square::F6)));
+        assert_eq!(kiwipete.parse_san("Rb1"), Ok(Move::new(Square::A1, Square::B1)));
+
+        let knights = Game::game_from_fen("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1");
+        assert_eq!(knights.parse_san("Nd2"), Err(SanError::Ambiguous));
+        assert_eq!(knights.parse_san("Nbd2"), Ok(Move::new(Square::B1, Square::D2)));
+        assert_eq!(knights.parse_san("Nfd2"), Ok(Move::new(Square::F1, Square::D2)));
+
+        let promotion = Game::game_from_fen("1n5k/P7/8/8/8/8/8/K7 w - - 0 1");
+        assert_eq!(promotion.parse_san("axb8=N"), Ok(Move::promote(Square::A7, Square::B8, PieceType::Knight)));
+        assert_eq!(promotion.parse_san("a8Q+"), Ok(Move::promote(Square::A7, Square::A8, PieceType::Queen)));
+        assert_eq!(promotion.parse_san("a8"), Err(SanError::Illegal));
+        assert_eq!(promotion.parse_san("a8=K"), Err(SanError::Malformed));
+    }
+
+    #[test]
+    pub fn to_san() {
+        let new_game = Game::new_game();
+        assert_eq!(new_game.to_san(&Move::new(Square::E2, Square::E4)), "e4");
+        assert_eq!(new_game.to_san(&Move::new(Square::G1, Square::F3)), "Nf3");
+
+        let kiwipete = Game::game_from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        assert_eq!(kiwipete.to_san(&Move::new(Square::E1, Square::G1)), "O-O");
+        assert_eq!(kiwipete.to_san(&Move::new(Square::E1, Square::C1)), "O-O-O");
+        assert_eq!(kiwipete.to_san(&Move::new(Square::D5, Square::E6)), "dxe6");
+        assert_eq!(kiwipete.to_san(&Move::new(Square::E5, Square::F7)), "Nxf7");
+
+        // disambiguation by file, by rank, and by both
+        let rooks = Game::game_from_fen("R6R/8/4k3/8/8/8/8/R3K3 w - - 0 1");
+        assert_eq!(rooks.to_san(&Move::new(Square::A8, Square::D8)), "Rad8");
+        assert_eq!(rooks.to_san(&Move::new(Square::A8, Square::A5)), "R8a5");
+        let queens = Game::game_from_fen("7k/8/8/8/Q1Q5/8/Q7/4K3 w - - 0 1");
+        assert_eq!(queens.to_san(&Move::new(Square::A4, Square::B3)), "Qa4b3");
+
+        let mut en_passant = Game::game_from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1");
+        assert_eq!(en_passant.to_san(&Move::new(Square::E5, Square::D6)), "exd6");
+        en_passant.do_move(Move::new(Square::E1, Square::E2)).unwrap();
+        assert_eq!(en_passant.to_san(&Move::new(Square::E8, Square::E7)), "Ke7");
+
+        let promotion = Game::game_from_fen("7k/P7/8/8/8/8/8/K7 w - - 0 1");
+        assert_eq!(promotion.to_san(&Move::promote(Square::A7, Square::A8, PieceType::Queen)), "a8=Q+");
+
+        let mut fools_mate = Game::new_game();
+        for san in ["f3", "e5", "g4"] {
+            fools_mate.do_move(fools_mate.parse_san(san).unwrap()).unwrap();
+        }
+        assert_eq!(fools_mate.to_san(&fools_mate.parse_san("Qh4").unwrap()), "Qh4#");
+    }
+}
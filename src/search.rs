@@ -0,0 +1,564 @@
+use std::cmp::{max, min, Reverse};
+use std::fmt;
+use std::mem::size_of;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::{Color, EvalWeights, Game, Move, PieceType};
+
+// deeper than any search will get in practice, and the longest line a search follows
+const MAX_DEPTH: u32 = 64;
+const MAX_PLY: usize = 128;
+const INFINITY: i32 = 32000;
+// the score for being mated right now, mates further away score a little less
+const MATE: i32 = 30000;
+// anything beyond this is a mate score
+const MATE_BOUND: i32 = MATE - MAX_PLY as i32;
+const DEFAULT_TABLE_MEGABYTES: usize = 16;
+
+// how far a search may go. it stops at whichever limit is reached first, and with none of them
+// set it goes on until it finds a forced mate or runs out of depth. the first iteration, at depth
+// 1, always finishes so there is a move to return
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct SearchLimits {
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    pub time: Option<Duration>,
+}
+
+impl SearchLimits {
+    // limits for a game on the clock, given the time left, the increment per move and how many
+    // moves are left until the next time control, if there is one
+    pub fn for_clock(remaining: Duration, increment: Duration, moves_to_go: Option<u32>) -> SearchLimits {
+        // without a time control to reach, plan for the game lasting a while longer
+        let moves = moves_to_go.unwrap_or(30).clamp(1, 50);
+        let time = remaining / moves + increment * 3 / 4;
+        // some time is lost passing the move on, and there should always be some left after
+        let most = remaining.saturating_sub(Duration::from_millis(50)) * 3 / 4;
+        SearchLimits { time: Some(min(time, most)), ..Default::default() }
+    }
+}
+
+// how good a position is for the side to move
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Score {
+    // in hundredths of a pawn
+    Centipawns(i32),
+    // mate in this many moves, negative when it's the side to move that gets mated
+    Mate(i32),
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Score::Centipawns(centipawns) => write!(f, "{:+.2}", *centipawns as f64 / 100.0),
+            Score::Mate(moves) => write!(f, "#{}", moves),
+        }
+    }
+}
+
+impl Score {
+    fn from_search(score: i32) -> Score {
+        if score.abs() < MATE_BOUND {
+            return Score::Centipawns(score);
+        }
+        // counted in moves of the side to move, so mate on the third ply is mate in 2
+        let moves = (MATE - score.abs() + 1) / 2;
+        Score::Mate(if score > 0 { moves } else { -moves })
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct SearchResult {
+    pub best_move: Move,
+    pub score: Score,
+    // the line the search expects to be played, starting with best_move
+    pub pv: Vec<Move>,
+    // how deep the last finished iteration went
+    pub depth: u32,
+    pub nodes: u64,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Bound {
+    Exact,
+    // the score is at least this, the search stopped at a move that was good enough
+    Lower,
+    // the score is at most this, no move reached alpha
+    Upper,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Entry {
+    key: u64,
+    mv: Option<Move>,
+    score: i32,
+    depth: i32,
+    bound: Bound,
+}
+
+// an iterative deepening alpha-beta search. it keeps its transposition table and move ordering
+// statistics from one search to the next, so reusing one Searcher through a game is faster
+// than calling Game::best_move each move
+pub struct Searcher {
+    weights: EvalWeights,
+    table: Vec<Option<Entry>>,
+    // two quiet moves per ply that caused a cutoff, tried early in sibling positions
+    killers: Vec<[Option<Move>; 2]>,
+    // how often a quiet move caused a cutoff, by color, from and to square
+    history: Box<[[[u32; 64]; 64]; 2]>,
+    // the hash keys of the positions before the current one, back through the game, for
+    // spotting repetitions
+    path: Vec<u64>,
+    // set from outside to stop the search early, see stop_flag
+    stop: Arc<AtomicBool>,
+    nodes: u64,
+    node_limit: Option<u64>,
+    deadline: Option<Instant>,
+    // the limits only apply once the first iteration is done
+    can_stop: bool,
+    stopped: bool,
+}
+
+impl Default for Searcher {
+    fn default() -> Searcher {
+        Searcher::new()
+    }
+}
+
+impl Searcher {
+    pub fn new() -> Searcher {
+        Searcher::with_weights(EvalWeights::default())
+    }
+
+    // a searcher that evaluates positions with the given weights instead of the default ones
+    pub fn with_weights(weights: EvalWeights) -> Searcher {
+        Searcher {
+            weights,
+            table: vec![None; DEFAULT_TABLE_MEGABYTES * 1024 * 1024 / size_of::<Option<Entry>>()],
+            killers: vec![[None; 2]; MAX_PLY],
+            history: Box::new([[[0; 64]; 64]; 2]),
+            path: Vec::new(),
+            stop: Arc::new(AtomicBool::new(false)),
+            nodes: 0,
+            node_limit: None,
+            deadline: None,
+            can_stop: false,
+            stopped: false,
+        }
+    }
+
+    // forgets everything learned in earlier searches, e.g. when starting a new game
+    pub fn clear(&mut self) {
+        self.table.fill(None);
+        self.killers.fill([None; 2]);
+        *self.history = [[[0; 64]; 64]; 2];
+    }
+
+    // resizes the transposition table, which forgets what is in it
+    pub fn set_table_size(&mut self, megabytes: usize) {
+        let entries = max(megabytes.saturating_mul(1024 * 1024) / size_of::<Option<Entry>>(), 1);
+        self.table = vec![None; entries];
+    }
+
+    // setting this to true from another thread stops a running search, which then returns the
+    // result of its last finished iteration. it isn't set back to false by the search, that is
+    // left to whoever set it
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    // searches for the best move for the side to move, None when the game is already over
+    pub fn search(&mut self, game: &Game, limits: SearchLimits) -> Option<SearchResult> {
+        self.search_with(game, limits, |_| {})
+    }
+
+    // search, calling `report` with the result of each iteration as it finishes, e.g. to show
+    // how the search is going
+    pub fn search_with(&mut self, game: &Game, limits: SearchLimits, mut report: impl FnMut(&SearchResult)) -> Option<SearchResult> {
+        let mut game = game.clone();
+        if game.legal_moves().is_empty() {
+            return None;
+        }
+        self.nodes = 0;
+        self.node_limit = limits.nodes;
+        self.deadline = limits.time.map(|time| Instant::now() + time);
+        self.can_stop = false;
+        self.stopped = false;
+        self.path = game.positions.clone();
+        self.killers.fill([None; 2]);
+        // older statistics count for less
+        for count in self.history.iter_mut().flatten().flatten() {
+            *count /= 8;
+        }
+
+        let max_depth = limits.depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH);
+        let mut result: Option<SearchResult> = None;
+        for depth in 1..=max_depth {
+            let mut pv = Vec::new();
+            let score = self.negamax(&mut game, depth as i32, 0, -INFINITY, INFINITY, &mut pv);
+            // an unfinished iteration can't be trusted, the last finished one is used instead
+            if self.stopped {
+                break;
+            }
+            self.complete_pv(&mut game, &mut pv, depth as usize);
+            let iteration = SearchResult {
+                best_move: pv[0],
+                score: Score::from_search(score),
+                pv,
+                depth,
+                nodes: self.nodes,
+            };
+            report(&iteration);
+            result = Some(iteration);
+            self.can_stop = true;
+            // a deeper search won't find a faster mate
+            if score.abs() >= MATE_BOUND && MATE - score.abs() <= depth as i32 {
+                break;
+            }
+        }
+        result.map(|result| SearchResult { nodes: self.nodes, ..result })
+    }
+
+    fn negamax(&mut self, game: &mut Game, depth: i32, ply: usize, mut alpha: i32, beta: i32, pv: &mut Vec<Move>) -> i32 {
+        pv.clear();
+        if self.should_stop() {
+            return 0;
+        }
+        self.nodes += 1;
+        let key = game.hash_key();
+        // the table tells positions apart by hash_key, repetitions go by repetition_key
+        let position = game.repetition_key();
+        if ply > 0 && self.is_draw(game, position) {
+            return 0;
+        }
+        let in_check = game.in_check();
+        // don't stop looking while in check, there could be a mate
+        let depth = if in_check { depth + 1 } else { depth };
+        if depth <= 0 || ply >= MAX_PLY {
+            return self.quiescence(game, ply, alpha, beta);
+        }
+
+        let mut table_move = None;
+        let slot = key as usize % self.table.len();
+        if let Some(entry) = self.table[slot].filter(|entry| entry.key == key) {
+            table_move = entry.mv;
+            let score = score_from_table(entry.score, ply);
+            let usable = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => score >= beta,
+                Bound::Upper => score <= alpha,
+            };
+            // at the root the whole line is needed, so search it anyway
+            if ply > 0 && entry.depth >= depth && usable {
+                return score;
+            }
+        }
+
+        let mut moves = Vec::new();
+        game.generate_moves(game.colors[game.turn as usize], &mut moves);
+        if moves.is_empty() {
+            return if in_check { -MATE + ply as i32 } else { 0 };
+        }
+        self.order_moves(game, &mut moves, table_move, ply);
+
+        let original_alpha = alpha;
+        let mut best = -INFINITY;
+        let mut best_move = None;
+        let mut line = Vec::new();
+        for mv in moves {
+            let quiet = captured(game, &mv).is_none() && mv.promotion.is_none();
+            let record = game.make_move(mv);
+            self.path.push(position);
+            let score = -self.negamax(game, depth - 1, ply + 1, -beta, -alpha, &mut line);
+            self.path.pop();
+            game.unmake_move(&record);
+            if self.stopped {
+                return 0;
+            }
+            if score > best {
+                best = score;
+                best_move = Some(mv);
+            }
+            if score > alpha {
+                alpha = score;
+                pv.clear();
+                pv.push(mv);
+                pv.extend_from_slice(&line);
+            }
+            if score >= beta {
+                if quiet {
+                    self.remember_cutoff(game.turn, mv, depth, ply);
+                }
+                break;
+            }
+        }
+
+        let bound = if best >= beta {
+            Bound::Lower
+        }
+        else if best > original_alpha {
+            Bound::Exact
+        }
+        else {
+            Bound::Upper
+        };
+        self.table[slot] = Some(Entry {
+            key,
+            mv: best_move,
+            score: score_to_table(best, ply),
+            depth,
+            bound,
+        });
+        best
+    }
+
+    // the line stops short where the search took a score from the transposition table, the
+    // moves stored there show how it goes on
+    fn complete_pv(&self, game: &mut Game, pv: &mut Vec<Move>, depth: usize) {
+        let mut records = Vec::new();
+        let mut seen = Vec::new();
+        for &mv in pv.iter() {
+            seen.push(game.hash_key());
+            records.push(game.make_move(mv));
+        }
+        while pv.len() < depth {
+            let key = game.hash_key();
+            let next = self.table[key as usize % self.table.len()]
+                .filter(|entry| entry.key == key)
+                .and_then(|entry| entry.mv)
+                .filter(|mv| game.legal_moves().contains(mv));
+            // a repetition would go on forever
+            let Some(mv) = next.filter(|_| !seen.contains(&key)) else { break };
+            seen.push(key);
+            records.push(game.make_move(mv));
+            pv.push(mv);
+        }
+        for record in records.iter().rev() {
+            game.unmake_move(record);
+        }
+    }
+
+    // only follows captures and queen promotions, so the evaluation isn't taken in the middle of
+    // an exchange. the side to move can always choose not to capture, so the evaluation of the
+    // position itself is a lower bound
+    fn quiescence(&mut self, game: &mut Game, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        if self.should_stop() {
+            return 0;
+        }
+        self.nodes += 1;
+        let stand_pat = game.evaluation(&self.weights).total();
+        if stand_pat >= beta || ply >= MAX_PLY {
+            return stand_pat;
+        }
+        alpha = max(alpha, stand_pat);
+
+        let mut moves = Vec::new();
+        game.generate_moves(game.colors[game.turn as usize], &mut moves);
+        moves.retain(|mv| captured(game, mv).is_some() || mv.promotion == Some(PieceType::Queen));
+        moves.sort_by_cached_key(|mv| Reverse(mvv_lva(game, mv)));
+
+        let mut best = stand_pat;
+        for mv in moves {
+            let record = game.make_move(mv);
+            let score = -self.quiescence(game, ply + 1, -beta, -alpha);
+            game.unmake_move(&record);
+            if self.stopped {
+                return 0;
+            }
+            best = max(best, score);
+            alpha = max(alpha, score);
+            if score >= beta {
+                break;
+            }
+        }
+        best
+    }
+
+    // the move from the transposition table first, then captures with the most valuable victim
+    // and least valuable attacker first, then killers, then the other quiet moves by history
+    fn order_moves(&self, game: &Game, moves: &mut [Move], table_move: Option<Move>, ply: usize) {
+        let killers = self.killers[ply];
+        let history = &self.history[game.turn as usize];
+        moves.sort_by_cached_key(|mv| {
+            let order = if Some(*mv) == table_move {
+                3_000_000
+            }
+            else if captured(game, mv).is_some() || mv.promotion.is_some() {
+                2_000_000 + mvv_lva(game, mv)
+            }
+            else if Some(*mv) == killers[0] {
+                1_500_000
+            }
+            else if Some(*mv) == killers[1] {
+                1_400_000
+            }
+            else {
+                min(history[mv.from.index()][mv.to.index()], 1_000_000) as i32
+            };
+            Reverse(order)
+        });
+    }
+
+    fn remember_cutoff(&mut self, color: Color, mv: Move, depth: i32, ply: usize) {
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(mv) {
+            killers[1] = killers[0];
+            killers[0] = Some(mv);
+        }
+        let count = &mut self.history[color as usize][mv.from.index()][mv.to.index()];
+        *count = count.saturating_add((depth * depth) as u32);
+    }
+
+    // a repetition counts as a draw the first time it comes up in the search, playing for it
+    // again gives the opponent the choice of claiming it
+    fn is_draw(&self, game: &Game, key: u64) -> bool {
+        if game.halfmove_clock >= 100 {
+            return true;
+        }
+        if game.is_insufficient_material(Color::White) && game.is_insufficient_material(Color::Black) {
+            return true;
+        }
+        // a capture or pawn move can never be undone, so only look back to the last one
+        let reversible = min(game.halfmove_clock as usize, self.path.len());
+        self.path[self.path.len() - reversible..].contains(&key)
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if self.stopped {
+            return true;
+        }
+        if !self.can_stop {
+            return false;
+        }
+        if self.node_limit.is_some_and(|limit| self.nodes >= limit) || self.stop.load(Ordering::Relaxed) {
+            self.stopped = true;
+        }
+        // looking at the clock every node would slow things down
+        if self.nodes.is_multiple_of(1024) && self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            self.stopped = true;
+        }
+        self.stopped
+    }
+}
+
+impl Game {
+    // searches for the best move for the side to move with a fresh Searcher, None when the game
+    // is already over:
+    //
+    //     let limits = SearchLimits { depth: Some(6), ..Default::default() };
+    //     if let Some(result) = game.best_move(limits) {
+    //         println!("{} {}", result.best_move, result.score);
+    //     }
+    pub fn best_move(&self, limits: SearchLimits) -> Option<SearchResult> {
+        Searcher::new().search(self, limits)
+    }
+}
+
+fn captured(game: &Game, mv: &Move) -> Option<PieceType> {
+    match game.piece_at(mv.to) {
+        Some(piece) => Some(piece.piece),
+        // en passant, the only way a pawn moves sideways onto an empty square
+        None if mv.from.file() != mv.to.file() && game.piece_at(mv.from).is_some_and(|piece| piece.piece == PieceType::Pawn) => Some(PieceType::Pawn),
+        None => None,
+    }
+}
+
+fn mvv_lva(game: &Game, mv: &Move) -> i32 {
+    let victim = captured(game, mv).map_or(0, PieceType::value);
+    let promotion = mv.promotion.map_or(0, PieceType::value);
+    let attacker = game.piece_at(mv.from).map_or(0, |piece| piece.piece as i32);
+    10 * (victim + promotion) - attacker
+}
+
+// mate scores are stored as the distance from the position in the table rather than from the
+// root, since the same position can come up at different plies
+fn score_to_table(score: i32, ply: usize) -> i32 {
+    if score >= MATE_BOUND {
+        score + ply as i32
+    }
+    else if score <= -MATE_BOUND {
+        score - ply as i32
+    }
+    else {
+        score
+    }
+}
+
+fn score_from_table(score: i32, ply: usize) -> i32 {
+    if score >= MATE_BOUND {
+        score - ply as i32
+    }
+    else if score <= -MATE_BOUND {
+        score + ply as i32
+    }
+    else {
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Square;
+
+    fn to_depth(depth: u32) -> SearchLimits {
+        SearchLimits { depth: Some(depth), ..Default::default() }
+    }
+
+    #[test]
+    pub fn find_mates() {
+        // back rank mate
+        let game = Game::game_from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        let result = game.best_move(to_depth(4)).unwrap();
+        assert_eq!(result.best_move, Move::new(Square::A1, Square::A8));
+        assert_eq!(result.score, Score::Mate(1));
+
+        // the side getting mated sees it coming
+        let game = Game::game_from_fen("7k/8/6K1/8/8/8/8/R7 b - - 0 1");
+        let result = game.best_move(to_depth(4)).unwrap();
+        assert_eq!(result.score, Score::Mate(-1));
+        assert_eq!(result.pv, vec![Move::new(Square::H8, Square::G8), Move::new(Square::A1, Square::A8)]);
+
+        // mate in 2, the queen is given up so the rook blocks in its own king
+        let game = Game::game_from_fen("3r3k/6pp/7N/8/8/1Q6/8/7K w - - 0 1");
+        let result = game.best_move(to_depth(5)).unwrap();
+        assert_eq!(result.score, Score::Mate(2));
+        let mut line = game.clone();
+        for mv in &result.pv {
+            line.do_move(*mv).unwrap();
+        }
+        assert_eq!(line.termination(), Some(crate::Termination::Checkmate));
+    }
+
+    #[test]
+    pub fn search_material() {
+        // the undefended queen is taken, the defended one isn't
+        let game = Game::game_from_fen("4k3/8/8/3q4/8/8/3R4/3K4 w - - 0 1");
+        let result = game.best_move(to_depth(3)).unwrap();
+        assert_eq!(result.best_move, Move::new(Square::D2, Square::D5));
+        assert!(matches!(result.score, Score::Centipawns(score) if score > 300));
+
+        let game = Game::game_from_fen("4k3/4r3/8/4n3/8/8/4Q3/4K3 w - - 0 1");
+        let result = game.best_move(to_depth(3)).unwrap();
+        assert_ne!(result.best_move, Move::new(Square::E2, Square::E5));
+    }
+
+    #[test]
+    pub fn search_limits() {
+        let game = Game::new_game();
+        let result = game.best_move(SearchLimits { nodes: Some(2000), ..Default::default() }).unwrap();
+        assert!(game.legal_moves().contains(&result.best_move));
+        // the limit is checked as the search goes, going over it a little
+        assert!(result.nodes < 4000);
+
+        let result = game.best_move(SearchLimits { time: Some(Duration::from_millis(50)), ..Default::default() }).unwrap();
+        assert!(result.depth >= 1);
+
+        // nothing to search once the game is over
+        let game = Game::game_from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1");
+        assert_eq!(game.best_move(to_depth(3)), None);
+        assert_eq!(format!("{} {} {}", Score::Centipawns(35), Score::Centipawns(-120), Score::Mate(-2)), "+0.35 -1.20 #-2");
+    }
+}
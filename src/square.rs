@@ -0,0 +1,170 @@
+use std::fmt;
+use std::str::FromStr;
+
+// a square of the board, from a1 to h8. it can only ever hold one of the 64 squares, so anything
+// taking a Square never has to check whether it is on the board
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Square(u8);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ParseSquareError;
+
+impl fmt::Display for ParseSquareError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "not a square, expected a file a-h followed by a rank 1-8")
+    }
+}
+
+impl std::error::Error for ParseSquareError {}
+
+impl Square {
+    pub const A1: Square = Square(0);
+    pub const B1: Square = Square(1);
+    pub const C1: Square = Square(2);
+    pub const D1: Square = Square(3);
+    pub const E1: Square = Square(4);
+    pub const F1: Square = Square(5);
+    pub const G1: Square = Square(6);
+    pub const H1: Square = Square(7);
+    pub const A2: Square = Square(8);
+    pub const B2: Square = Square(9);
+    pub const C2: Square = Square(10);
+    pub const D2: Square = Square(11);
+    pub const E2: Square = Square(12);
+    pub const F2: Square = Square(13);
+    pub const G2: Square = Square(14);
+    pub const H2: Square = Square(15);
+    pub const A3: Square = Square(16);
+    pub const B3: Square = Square(17);
+    pub const C3: Square = Square(18);
+    pub const D3: Square = Square(19);
+    pub const E3: Square = Square(20);
+    pub const F3: Square = Square(21);
+    pub const G3: Square = Square(22);
+    pub const H3: Square = Square(23);
+    pub const A4: Square = Square(24);
+    pub const B4: Square = Square(25);
+    pub const C4: Square = Square(26);
+    pub const D4: Square = Square(27);
+    pub const E4: Square = Square(28);
+    pub const F4: Square = Square(29);
+    pub const G4: Square = Square(30);
+    pub const H4: Square = Square(31);
+    pub const A5: Square = Square(32);
+    pub const B5: Square = Square(33);
+    pub const C5: Square = Square(34);
+    pub const D5: Square = Square(35);
+    pub const E5: Square = Square(36);
+    pub const F5: Square = Square(37);
+    pub const G5: Square = Square(38);
+    pub const H5: Square = Square(39);
+    pub const A6: Square = Square(40);
+    pub const B6: Square = Square(41);
+    pub const C6: Square = Square(42);
+    pub const D6: Square = Square(43);
+    pub const E6: Square = Square(44);
+    pub const F6: Square = Square(45);
+    pub const G6: Square = Square(46);
+    pub const H6: Square = Square(47);
+    pub const A7: Square = Square(48);
+    pub const B7: Square = Square(49);
+    pub const C7: Square = Square(50);
+    pub const D7: Square = Square(51);
+    pub const E7: Square = Square(52);
+    pub const F7: Square = Square(53);
+    pub const G7: Square = Square(54);
+    pub const H7: Square = Square(55);
+    pub const A8: Square = Square(56);
+    pub const B8: Square = Square(57);
+    pub const C8: Square = Square(58);
+    pub const D8: Square = Square(59);
+    pub const E8: Square = Square(60);
+    pub const F8: Square = Square(61);
+    pub const G8: Square = Square(62);
+    pub const H8: Square = Square(63);
+
+    // file 0 is the a-file and rank 0 is the first rank, None if either is past 7
+    pub fn new(file: usize, rank: usize) -> Option<Square> {
+        if file < 8 && rank < 8 {
+            Some(Square((rank * 8 + file) as u8))
+        }
+        else {
+            None
+        }
+    }
+
+    pub fn file(self) -> usize {
+        self.0 as usize % 8
+    }
+
+    pub fn rank(self) -> usize {
+        self.0 as usize / 8
+    }
+
+    // 0 for a1, 1 for b1, ... and 63 for h8
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    pub(crate) fn from_index(index: usize) -> Square {
+        debug_assert!(index < 64);
+        Square(index as u8)
+    }
+
+    // the squares from a1, b1, ... to h8
+    pub fn all() -> impl Iterator<Item = Square> {
+        (0..64).map(Square)
+    }
+
+    // where the square is in a Board, which is indexed [y][x] with the files mirrored
+    pub(crate) fn x(self) -> usize {
+        7 - self.file()
+    }
+
+    pub(crate) fn y(self) -> usize {
+        self.rank()
+    }
+}
+
+impl FromStr for Square {
+    type Err = ParseSquareError;
+
+    fn from_str(s: &str) -> Result<Square, ParseSquareError> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next(), chars.next()) {
+            (Some(file @ 'a'..='h'), Some(rank @ '1'..='8'), None) => {
+                Square::new(file as usize - 'a' as usize, rank as usize - '1' as usize).ok_or(ParseSquareError)
+            }
+            _ => Err(ParseSquareError),
+        }
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", (b'a' + self.file() as u8) as char, self.rank() + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn square_names() {
+        assert_eq!("e4".parse(), Ok(Square::E4));
+        assert_eq!(Square::from_str("a1"), Ok(Square::A1));
+        assert_eq!(Square::from_str("h8"), Ok(Square::H8));
+        assert_eq!(Square::from_str("i1"), Err(ParseSquareError));
+        assert_eq!(Square::from_str("a9"), Err(ParseSquareError));
+        assert_eq!(Square::from_str("e44"), Err(ParseSquareError));
+        assert_eq!(Square::E4.to_string(), "e4");
+        assert_eq!((Square::E4.file(), Square::E4.rank()), (4, 3));
+        assert_eq!(Square::new(4, 3), Some(Square::E4));
+        assert_eq!(Square::new(8, 0), None);
+        assert!(Square::all().all(|square| Square::from_str(&square.to_string()) == Ok(square)));
+        // the board array has the files mirrored
+        assert_eq!((Square::E4.x(), Square::E4.y()), (3, 3));
+        assert_eq!((Square::A1.x(), Square::H8.x()), (7, 0));
+    }
+}
@@ -0,0 +1,152 @@
+use std::fmt;
+
+use crate::{Color, Game, Move, Piece, PieceType, Square};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum UciMoveError {
+    // a move is four characters, five with a promotion
+    WrongLength,
+    InvalidSquare,
+    InvalidPromotion,
+}
+
+impl fmt::Display for UciMoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UciMoveError::WrongLength => write!(f, "a move has to be 4 or 5 characters long"),
+            UciMoveError::InvalidSquare => write!(f, "invalid square"),
+            UciMoveError::InvalidPromotion => write!(f, "invalid promotion piece"),
+        }
+    }
+}
+
+impl std::error::Error for UciMoveError {}
+
+// how castling is written, the king moving two squares ("e1g1") or, as in Chess960, the king
+// capturing its own rook ("e1h1")
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CastlingNotation {
+    KingTwoSquares,
+    KingTakesRook,
+}
+
+impl Move {
+    // reads a move in the long algebraic notation used by UCI, like "e2e4" or "e7e8q"
+    pub fn from_uci(s: &str) -> Result<Move, UciMoveError> {
+        if !s.is_ascii() || (s.len() != 4 && s.len() != 5) {
+            return Err(UciMoveError::WrongLength);
+        }
+        let from: Square = s[0..2].parse().map_err(|_| UciMoveError::InvalidSquare)?;
+        let to: Square = s[2..4].parse().map_err(|_| UciMoveError::InvalidSquare)?;
+        let promotion = match s[4..].chars().next() {
+            None => None,
+            Some('n') => Some(PieceType::Knight),
+            Some('b') => Some(PieceType::Bishop),
+            Some('r') => Some(PieceType::Rook),
+            Some('q') => Some(PieceType::Queen),
+            Some(_) => return Err(UciMoveError::InvalidPromotion),
+        };
+        Ok(Move { from, to, promotion })
+    }
+
+    pub fn to_uci(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.from, self.to)?;
+        match self.promotion {
+            Some(PieceType::Knight) => write!(f, "n"),
+            Some(PieceType::Bishop) => write!(f, "b"),
+            Some(PieceType::Rook) => write!(f, "r"),
+            Some(PieceType::Queen) => write!(f, "q"),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Game {
+    // reads a UCI move for the current position, castling may be written either way
+    pub fn parse_uci(&self, s: &str) -> Result<Move, UciMoveError> {
+        let mut mv = Move::from_uci(s)?;
+        // a king "capturing" its own rook is castling, but only from the squares castling starts
+        // from and with the right to castle that way. anything else is left for do_move to reject
+        let king = Some(Piece{piece: PieceType::King, color: self.turn});
+        let rook = Some(Piece{piece: PieceType::Rook, color: self.turn});
+        let (king_start, king_side_rook, queen_side_rook) = match self.turn {
+            Color::White => (Square::E1, Square::H1, Square::A1),
+            Color::Black => (Square::E8, Square::H8, Square::A8),
+        };
+        let (king_side, queen_side) = match self.turn {
+            Color::White => (self.castling.white_king_side, self.castling.white_queen_side),
+            Color::Black => (self.castling.black_king_side, self.castling.black_queen_side),
+        };
+        if mv.from == king_start && self.piece_at(mv.from) == king && self.piece_at(mv.to) == rook {
+            if mv.to == king_side_rook && king_side {
+                mv.to = Square::new(6, king_start.rank()).unwrap();
+            }
+            else if mv.to == queen_side_rook && queen_side {
+                mv.to = Square::new(2, king_start.rank()).unwrap();
+            }
+        }
+        Ok(mv)
+    }
+
+    // writes a move of the current position in UCI notation
+    pub fn to_uci(&self, mv: &Move, castling: CastlingNotation) -> String {
+        let mut mv = *mv;
+        if castling == CastlingNotation::KingTakesRook && Game::is_castling(&mv, &self.piece_at(mv.from)) {
+            // the rooks start on the a- and h-files
+            let file = if mv.to.file() > mv.from.file() { 7 } else { 0 };
+            mv.to = Square::new(file, mv.from.rank()).unwrap();
+        }
+        mv.to_uci()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn uci_moves() {
+        assert_eq!(Move::from_uci("e2e4"), Ok(Move::new(Square::E2, Square::E4)));
+        assert_eq!(Move::from_uci("a7b8n"), Ok(Move::promote(Square::A7, Square::B8, PieceType::Knight)));
+        assert_eq!(Move::new(Square::E2, Square::E4).to_string(), "e2e4");
+        assert_eq!(Move::promote(Square::H7, Square::H8, PieceType::Queen).to_uci(), "h7h8q");
+        assert_eq!(Move::from_uci("e2e"), Err(UciMoveError::WrongLength));
+        assert_eq!(Move::from_uci("e2e4qq"), Err(UciMoveError::WrongLength));
+        assert_eq!(Move::from_uci("e2e9"), Err(UciMoveError::InvalidSquare));
+        assert_eq!(Move::from_uci("i2e4"), Err(UciMoveError::InvalidSquare));
+        assert_eq!(Move::from_uci("e7e8k"), Err(UciMoveError::InvalidPromotion));
+        assert_eq!(Move::from_uci("é2e4"), Err(UciMoveError::WrongLength));
+    }
+
+    #[test]
+    pub fn uci_castling() {
+        let castle = Game::game_from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        let king_side = Move::new(Square::E1, Square::G1);
+        let queen_side = Move::new(Square::E1, Square::C1);
+        assert_eq!(castle.parse_uci("e1g1"), Ok(king_side));
+        assert_eq!(castle.parse_uci("e1h1"), Ok(king_side));
+        assert_eq!(castle.parse_uci("e1a1"), Ok(queen_side));
+        assert_eq!(castle.to_uci(&king_side, CastlingNotation::KingTwoSquares), "e1g1");
+        assert_eq!(castle.to_uci(&king_side, CastlingNotation::KingTakesRook), "e1h1");
+        assert_eq!(castle.to_uci(&queen_side, CastlingNotation::KingTakesRook), "e1a1");
+        assert_eq!(castle.to_uci(&Move::new(Square::E1, Square::F1), CastlingNotation::KingTakesRook), "e1f1");
+
+        // a king next to its own rook, or without the right to castle, isn't castling
+        for (fen, uci, mv) in [
+            ("7k/8/8/8/8/8/8/RK6 w - - 0 1", "b1a1", Move::new(Square::B1, Square::A1)),
+            ("7k/8/8/8/8/8/8/6KR w - - 0 1", "g1h1", Move::new(Square::G1, Square::H1)),
+            ("r3k2r/8/8/8/8/8/8/R3K2R w Qkq - 0 1", "e1h1", Move::new(Square::E1, Square::H1)),
+        ] {
+            let mut game = Game::game_from_fen(fen);
+            assert_eq!(game.parse_uci(uci), Ok(mv));
+            assert!(game.do_move(mv).is_err());
+        }
+        assert_eq!(Game::game_from_fen("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1").parse_uci("e8a8"), Ok(Move::new(Square::E8, Square::C8)));
+    }
+}
@@ -0,0 +1,133 @@
+use std::fmt;
+
+use crate::bitboard::{RANK_1, RANK_8};
+use crate::{Color, Game, Piece, PieceType, Square};
+
+// the ways a position can be impossible to reach in a real game
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PositionError {
+    MissingKing(Color),
+    TooManyKings(Color),
+    TooManyPieces(Color, PieceType),
+    PawnOnBackRank(Color),
+    OpponentInCheck,
+    InvalidCastlingRights,
+    InvalidEnPassant,
+}
+
+impl fmt::Display for PositionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PositionError::MissingKing(color) => write!(f, "{:?} has no king", color),
+            PositionError::TooManyKings(color) => write!(f, "{:?} has more than one king", color),
+            PositionError::TooManyPieces(color, piece) => write!(f, "{:?} has too many pieces of type {:?}", color, piece),
+            PositionError::PawnOnBackRank(color) => write!(f, "{:?} has a pawn on the first or last rank", color),
+            PositionError::OpponentInCheck => write!(f, "the side not to move is in check"),
+            PositionError::InvalidCastlingRights => write!(f, "castling rights without the king and rook on their starting squares"),
+            PositionError::InvalidEnPassant => write!(f, "en passant square without a pawn that just moved two squares"),
+        }
+    }
+}
+
+impl std::error::Error for PositionError {}
+
+impl Game {
+    // checks that the position could come up in a real game, useful after game_from_fen or after
+    // setting up the board by hand
+    pub fn validate(&self) -> Result<(), PositionError> {
+        for color in [Color::White, Color::Black] {
+            self.validate_pieces(color)?;
+        }
+
+        // the rook and king have to be standing on their starting squares for a right to be kept
+        let home = |square: Square, piece: PieceType, color: Color| self.piece_at(square) == Some(Piece{piece, color});
+        let castling = [
+            (self.castling.white_king_side, Square::E1, Square::H1, Color::White),
+            (self.castling.white_queen_side, Square::E1, Square::A1, Color::White),
+            (self.castling.black_king_side, Square::E8, Square::H8, Color::Black),
+            (self.castling.black_queen_side, Square::E8, Square::A8, Color::Black),
+        ];
+        for (allowed, king, rook, color) in castling {
+            if allowed && !(home(king, PieceType::King, color) && home(rook, PieceType::Rook, color)) {
+                return Err(PositionError::InvalidCastlingRights);
+            }
+        }
+
+        // the pawn that just moved two squares has to be in front of the skipped square, with
+        // both the skipped square and the square it came from empty
+        if let Some(square) = self.en_passant {
+            let (rank, pawn_rank, from_rank) = match self.turn {
+                Color::White => (5, 4, 6),
+                Color::Black => (2, 3, 1),
+            };
+            let on_file = |rank| self.piece_at(Square::new(square.file(), rank).unwrap());
+            if square.rank() != rank
+                || on_file(pawn_rank) != Some(Piece{piece: PieceType::Pawn, color: self.turn.opposite()})
+                || self.piece_at(square).is_some()
+                || on_file(from_rank).is_some() {
+                return Err(PositionError::InvalidEnPassant);
+            }
+        }
+
+        // the side that just moved can't have left its own king in check
+        let king = self.king_square(self.turn.opposite()).unwrap();
+        if self.square_attacked(king, self.turn) {
+            return Err(PositionError::OpponentInCheck);
+        }
+        Ok(())
+    }
+
+    fn validate_pieces(&self, color: Color) -> Result<(), PositionError> {
+        let count = |piece: PieceType| self.pieces_of(piece, color).count_ones();
+        match count(PieceType::King) {
+            0 => return Err(PositionError::MissingKing(color)),
+            1 => {},
+            _ => return Err(PositionError::TooManyKings(color)),
+        }
+        let pawns = count(PieceType::Pawn);
+        if pawns > 8 {
+            return Err(PositionError::TooManyPieces(color, PieceType::Pawn));
+        }
+        // every piece beyond the starting set has to come from a promoted pawn
+        let mut promoted = 0;
+        for (piece, start) in [(PieceType::Queen, 1), (PieceType::Rook, 2), (PieceType::Bishop, 2), (PieceType::Knight, 2)] {
+            promoted += count(piece).saturating_sub(start);
+            if pawns + promoted > 8 {
+                return Err(PositionError::TooManyPieces(color, piece));
+            }
+        }
+        if self.pieces_of(PieceType::Pawn, color) & (RANK_1 | RANK_8) != 0 {
+            return Err(PositionError::PawnOnBackRank(color));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position_error(fen: &str) -> Result<(), PositionError> {
+        Game::game_from_fen(fen).validate()
+    }
+
+    #[test]
+    pub fn valid_positions() {
+        assert_eq!(Game::new_game().validate(), Ok(()));
+        assert_eq!(position_error("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"), Ok(()));
+        assert_eq!(position_error("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3"), Ok(()));
+        assert_eq!(position_error("QQQQ1k2/QQQQ4/8/8/8/8/8/K7 b - - 0 1"), Ok(()));
+    }
+
+    #[test]
+    pub fn invalid_positions() {
+        assert_eq!(Game::empty_game().validate(), Err(PositionError::MissingKing(Color::White)));
+        assert_eq!(position_error("k7/8/8/8/8/8/8/KK6 w - - 0 1"), Err(PositionError::TooManyKings(Color::White)));
+        assert_eq!(position_error("k7/pppppppp/p7/8/8/8/8/K7 w - - 0 1"), Err(PositionError::TooManyPieces(Color::Black, PieceType::Pawn)));
+        assert_eq!(position_error("k7/8/8/8/8/8/PPPPPPPP/KNNN4 w - - 0 1"), Err(PositionError::TooManyPieces(Color::White, PieceType::Knight)));
+        assert_eq!(position_error("k7/8/8/8/8/8/8/KP6 w - - 0 1"), Err(PositionError::PawnOnBackRank(Color::White)));
+        assert_eq!(position_error("k7/8/8/8/8/8/8/R6K w - - 0 1"), Err(PositionError::OpponentInCheck));
+        assert_eq!(position_error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN1 w KQkq - 0 1"), Err(PositionError::InvalidCastlingRights));
+        assert_eq!(position_error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq d6 0 1"), Err(PositionError::InvalidEnPassant));
+    }
+}